@@ -0,0 +1,43 @@
+use std::env::args;
+use std::fs::read_to_string;
+
+use cosmwasm_std::to_vec;
+use serde::Serialize;
+
+use cw_starter::helpers::{merkle_tree, merkle_voters};
+use cw_starter::msg::MerkleProof;
+
+// Builds the merkle tree of a poll from a CSV file with a line of "address" or
+// "address,weight" per voter, and prints the root with the proof of every voter.
+// cargo run --example merkle -- examples/merkle_voters.csv
+
+#[derive(Serialize)]
+struct Tree {
+    merkle_root: String,
+    proofs: Vec<VoterProof>,
+}
+
+#[derive(Serialize)]
+struct VoterProof {
+    address: String,
+    merkle_proof: MerkleProof,
+}
+
+fn main() {
+    let path = args().nth(1).expect("usage: merkle <csv file>");
+    let voters = merkle_voters(&read_to_string(&path).unwrap()).unwrap();
+    let (merkle_root, proofs) = merkle_tree(&voters).unwrap();
+
+    let tree = Tree {
+        merkle_root,
+        proofs: voters
+            .into_iter()
+            .zip(proofs)
+            .map(|((address, _), merkle_proof)| VoterProof {
+                address,
+                merkle_proof,
+            })
+            .collect(),
+    };
+    println!("{}", String::from_utf8(to_vec(&tree).unwrap()).unwrap());
+}
@@ -6,7 +6,7 @@ use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 // 12 QueryMsg
 // - use cw_starter::msg::{CustomResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
 // + use cw_starter::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use cw_starter::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw_starter::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 // 06 Instantiate
 // - use cw_starter::state::State;
 // + use cw_starter::state::{Config, Poll, Ballot};
@@ -21,6 +21,7 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     // 06 Instantiate
     // - export_schema(&schema_for!(State), &out_dir);
     // + export_schema(&schema_for!(Config), &out_dir);
@@ -4,12 +4,19 @@ use cosmwasm_std::entry_point;
 // - use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
 // + use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Order, to_binary};
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    coins, from_binary, to_binary, Addr, BankMsg, Binary, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
 // 06 Instantiate
 // - // use cw2::set_contract_version;
 // + use cw2::set_contract_version;
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw4::Cw4Contract;
+use cw721::{Cw721QueryMsg, OwnerOfResponse, TokensResponse};
+use cw_storage_plus::Bound;
+use cw_utils::{must_pay, nonpayable, Expiration, PaymentError};
+use semver::Version;
 // 06 Instantiate
 // + use crate::state::{Config, CONFIG};
 // 09 Execute 1
@@ -18,14 +25,21 @@ use cw2::set_contract_version;
 // 10 Execute 2
 // - use crate::state::{Config, CONFIG, Poll, POLLS};
 // + use crate::state::{Config, Poll, CONFIG, POLLS, Ballot, BALLOTS};
-use crate::state::{Ballot, Config, Poll, BALLOTS, CONFIG, POLLS};
+use crate::state::{
+    ballots, polls, Ballot, Config, Poll, PollStatus, VoteWeight, VotingMode, CONFIG,
+    LEGACY_BALLOTS, LEGACY_CONFIG, LEGACY_POLLS, NFT_VOTES, PENDING_ADMIN, POLL_COUNT, POLL_VOTERS,
+    RANKED_VOTES,
+};
 
 use crate::error::ContractError;
+use crate::helpers::{merkle_leaf, merkle_parent};
 // 13 Query
 // - use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
 // + use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, AllPollsResponse, PollResponse, VoteResponse};
 use crate::msg::{
-    AllPollsResponse, ExecuteMsg, InstantiateMsg, PollResponse, QueryMsg, VoteResponse,
+    AllPollsResponse, Choice, ConfigResponse, CreatePollResponse, ExecuteMsg, InstantiateMsg,
+    ListVotesResponse, MerkleProof, MigrateMsg, PollResponse, QueryMsg, RankedResultResponse,
+    RankedRound, ReceiveMsg, VoteResponse, VoteWeightMsg, VotesByVoterResponse,
 };
 
 // 06 Instantiate
@@ -39,6 +53,16 @@ use crate::msg::{
 const CONTRACT_NAME: &str = "crates.io:cw-starter";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// Limits applied to new polls when none are given at instantiation
+const DEFAULT_MAX_OPTIONS: u32 = 10;
+const DEFAULT_MAX_QUESTION_LEN: u32 = 256;
+const DEFAULT_MAX_OPTION_LEN: u32 = 64;
+const DEFAULT_MAX_POLL_ID_LEN: u32 = 64;
+
+// Page size for queries returning lists, when no limit is given and the maximum allowed
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
 // 06 Instantiate
 // - _deps: DepsMut,
 // - _env: Env,
@@ -70,9 +94,25 @@ pub fn instantiate(
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     let admin = msg.admin.unwrap_or_else(|| info.sender.to_string());
     let validated_admin = deps.api.addr_validate(&admin)?;
+    let cw20_addr = match msg.cw20_addr {
+        Some(cw20_addr) => Some(deps.api.addr_validate(&cw20_addr)?),
+        None => None,
+    };
+    let group_addr = match msg.group_addr {
+        Some(group_addr) => Some(deps.api.addr_validate(&group_addr)?),
+        None => None,
+    };
     let config = Config {
-        admin: validated_admin.clone(),
+        admin: Some(validated_admin.clone()),
+        max_options: msg.max_options.unwrap_or(DEFAULT_MAX_OPTIONS),
+        max_question_len: msg.max_question_len.unwrap_or(DEFAULT_MAX_QUESTION_LEN),
+        max_option_len: msg.max_option_len.unwrap_or(DEFAULT_MAX_OPTION_LEN),
+        max_poll_id_len: msg.max_poll_id_len.unwrap_or(DEFAULT_MAX_POLL_ID_LEN),
+        cw20_addr,
+        group_addr,
+        members_only_create: msg.members_only_create.unwrap_or(false),
     };
+    validate_config(&config)?;
     CONFIG.save(deps.storage, &config)?;
     Ok(Response::new()
         .add_attribute("action", "instantiate")
@@ -110,11 +150,74 @@ pub fn execute(
             poll_id,
             question,
             options,
-        } => execute_create_poll(deps, env, info, poll_id, question, options),
+            start,
+            end,
+            voting_mode,
+            vote_weight,
+            voters,
+            merkle_root,
+        } => execute_create_poll(
+            deps,
+            env,
+            info,
+            poll_id,
+            question,
+            options,
+            start,
+            end,
+            voting_mode,
+            vote_weight,
+            voters,
+            merkle_root,
+        ),
         // 10 Execute 2
         // - ExecuteMsg::Vote { poll_id, vote } => unimplemented!(),
         // + ExecuteMsg::Vote { poll_id, vote } => execute_vote(deps, env, info, poll_id, vote),
-        ExecuteMsg::Vote { poll_id, vote } => execute_vote(deps, env, info, poll_id, vote),
+        ExecuteMsg::Vote {
+            poll_id,
+            vote,
+            token_ids,
+            merkle_proof,
+        } => execute_vote(deps, env, info, poll_id, vote, token_ids, merkle_proof),
+        ExecuteMsg::ClosePoll { poll_id } => execute_close_poll(deps, env, info, poll_id),
+        ExecuteMsg::FinalizePoll { poll_id } => execute_finalize_poll(deps, env, info, poll_id),
+        ExecuteMsg::AddVoters { poll_id, voters } => {
+            execute_update_voters(deps, env, info, poll_id, voters, true)
+        }
+        ExecuteMsg::RemoveVoters { poll_id, voters } => {
+            execute_update_voters(deps, env, info, poll_id, voters, false)
+        }
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::Withdraw { poll_id } => execute_withdraw(deps, env, info, poll_id),
+        ExecuteMsg::ProposeNewAdmin { new_admin } => {
+            execute_propose_new_admin(deps, env, info, new_admin)
+        }
+        ExecuteMsg::AcceptAdmin {} => execute_accept_admin(deps, env, info),
+        ExecuteMsg::RenounceAdmin {} => execute_renounce_admin(deps, env, info),
+        ExecuteMsg::UpdateConfig {
+            max_options,
+            max_question_len,
+            max_option_len,
+            max_poll_id_len,
+            cw20_addr,
+            remove_cw20,
+            group_addr,
+            remove_group,
+            members_only_create,
+        } => execute_update_config(
+            deps,
+            env,
+            info,
+            max_options,
+            max_question_len,
+            max_option_len,
+            max_poll_id_len,
+            cw20_addr,
+            remove_cw20,
+            group_addr,
+            remove_group,
+            members_only_create,
+        ),
     }
 }
 
@@ -146,35 +249,211 @@ pub fn execute(
 // +
 // +     Ok(Response::new())
 // + }
+#[allow(clippy::too_many_arguments)]
 fn execute_create_poll(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    poll_id: String,
+    poll_id: Option<String>,
     question: String,
     options: Vec<String>,
+    start: Option<Expiration>,
+    end: Option<Expiration>,
+    voting_mode: Option<VotingMode>,
+    vote_weight: Option<VoteWeightMsg>,
+    voters: Option<Vec<String>>,
+    merkle_root: Option<String>,
 ) -> Result<Response, ContractError> {
-    if options.len() > 10 {
+    let config = CONFIG.load(deps.storage)?;
+    if config.members_only_create {
+        member_weight(deps.as_ref(), &config, &info.sender, None)?;
+    }
+
+    if let Some(poll_id) = &poll_id {
+        if poll_id.trim().is_empty() {
+            return Err(ContractError::EmptyPollId {});
+        }
+        if poll_id.len() > config.max_poll_id_len as usize {
+            return Err(ContractError::PollIdTooLong {
+                poll_id: poll_id.clone(),
+            });
+        }
+        // Numeric ids are reserved for the ids assigned by the contract
+        if poll_id.parse::<u64>().is_ok() {
+            return Err(ContractError::ReservedPollId {
+                poll_id: poll_id.clone(),
+            });
+        }
+        // Saving over an existing poll would wipe its tally while its ballots remain
+        if polls().may_load(deps.storage, poll_id)?.is_some() {
+            return Err(ContractError::DuplicatePollId {
+                poll_id: poll_id.clone(),
+            });
+        }
+    }
+
+    if question.trim().is_empty() {
+        return Err(ContractError::EmptyQuestion {});
+    }
+    if question.len() > config.max_question_len as usize {
+        return Err(ContractError::QuestionTooLong {});
+    }
+    // Options are stored without surrounding whitespace, so names differing only in it
+    // count as duplicates
+    let options: Vec<String> = options
+        .iter()
+        .map(|option| option.trim().to_string())
+        .collect();
+    if options.len() < 2 {
+        return Err(ContractError::TooFewOptions {});
+    }
+    if options.len() > config.max_options as usize {
         return Err(ContractError::TooManyOptions {});
     }
+    if options.iter().any(|option| option.is_empty()) {
+        return Err(ContractError::EmptyOption {});
+    }
+    if let Some(option) = options
+        .iter()
+        .find(|option| option.len() > config.max_option_len as usize)
+    {
+        return Err(ContractError::OptionTooLong {
+            option: option.clone(),
+        });
+    }
+    // Votes find their option by name, so every name has to be unique
+    for (i, option) in options.iter().enumerate() {
+        if options[..i].contains(option) {
+            return Err(ContractError::DuplicateOption {
+                option: option.clone(),
+            });
+        }
+    }
+
+    let voting_mode = voting_mode.unwrap_or(VotingMode::SingleChoice);
+    if let VotingMode::Approval { max_choices } = voting_mode {
+        if max_choices == 0 || max_choices as usize > options.len() {
+            return Err(ContractError::InvalidMaxChoices {});
+        }
+    }
+
+    let vote_weight = match vote_weight.unwrap_or(VoteWeightMsg::Equal) {
+        VoteWeightMsg::Equal => VoteWeight::Equal,
+        VoteWeightMsg::Deposit { denom } => {
+            if denom.trim().is_empty() {
+                return Err(ContractError::EmptyDenom {});
+            }
+            VoteWeight::Deposit { denom }
+        }
+        VoteWeightMsg::Cw20 { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            if config.cw20_addr.as_ref() != Some(&address) {
+                return Err(ContractError::TokenNotWhitelisted {
+                    address: address.to_string(),
+                });
+            }
+            VoteWeight::Cw20 { address }
+        }
+        VoteWeightMsg::Cw721 { address, per_token } => VoteWeight::Cw721 {
+            address: deps.api.addr_validate(&address)?,
+            per_token,
+        },
+        VoteWeightMsg::Staked => VoteWeight::Staked,
+    };
+    // Credits come either from the budget or from deposits, never both
+    if let VotingMode::Quadratic { budget } = voting_mode {
+        let valid = match budget {
+            Some(budget) => !budget.is_zero() && vote_weight == VoteWeight::Equal,
+            None => vote_weight != VoteWeight::Equal,
+        };
+        if !valid {
+            return Err(ContractError::InvalidBudget {});
+        }
+    }
+
+    // A poll which never starts or has already ended could never be voted on
+    if start == Some(Expiration::Never {}) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+    if let Some(end) = end {
+        if end.is_expired(&env.block) {
+            return Err(ContractError::InvalidExpiration {});
+        }
+        // Mismatched heights and times cannot be compared, so those are allowed
+        if let Some(start) = start {
+            if start >= end {
+                return Err(ContractError::InvalidExpiration {});
+            }
+        }
+    }
 
-    let mut opts: Vec<(String, u64)> = vec![];
+    let voters = match voters {
+        Some(voters) => Some(
+            voters
+                .iter()
+                .map(|voter| deps.api.addr_validate(voter))
+                .collect::<StdResult<Vec<_>>>()?,
+        ),
+        None => None,
+    };
+
+    // Stored lowercase so it can be compared with the encoded root of a proof
+    let merkle_root = match merkle_root {
+        Some(merkle_root) => {
+            let mut root = [0u8; 32];
+            if hex::decode_to_slice(&merkle_root, &mut root).is_err() {
+                return Err(ContractError::InvalidMerkleRoot {});
+            }
+            Some(hex::encode(root))
+        }
+        None => None,
+    };
+
+    // Without a client provided id the next number in the sequence is used
+    let poll_id = match poll_id {
+        Some(poll_id) => poll_id,
+        None => {
+            let mut count = POLL_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+            // Polls migrated from 0.1 may already use numeric ids, those are skipped
+            while polls().has(deps.storage, &count.to_string()) {
+                count += 1;
+            }
+            POLL_COUNT.save(deps.storage, &count)?;
+            count.to_string()
+        }
+    };
+
+    let mut opts: Vec<(String, Uint128)> = vec![];
     for option in options {
-        opts.push((option, 0));
+        opts.push((option, Uint128::zero()));
     }
 
     let poll = Poll {
         creator: info.sender,
         question,
         options: opts,
+        status: PollStatus::Open,
+        start,
+        end,
+        voting_mode,
+        vote_weight,
+        snapshot_height: env.block.height,
+        allowlist: voters.is_some(),
+        merkle_root,
     };
 
     // 15 Spring Cleaning
     // - POLLS.save(deps.storage, poll_id, &poll)?;
     // + POLLS.save(deps.storage, &poll_id, &poll)?;
-    POLLS.save(deps.storage, &poll_id, &poll)?;
+    polls().save(deps.storage, &poll_id, &poll)?;
+    for voter in voters.unwrap_or_default() {
+        POLL_VOTERS.save(deps.storage, (&poll_id, &voter), &())?;
+    }
 
-    Ok(Response::new())
+    Ok(Response::new()
+        .add_attribute("action", "create_poll")
+        .add_attribute("poll_id", poll_id.clone())
+        .set_data(to_binary(&CreatePollResponse { poll_id })?))
 }
 
 // 10 Execute 2
@@ -237,263 +516,2705 @@ fn execute_create_poll(
 // + }
 fn execute_vote(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     poll_id: String,
-    vote: String,
+    vote: Choice,
+    token_ids: Option<Vec<String>>,
+    merkle_proof: Option<MerkleProof>,
 ) -> Result<Response, ContractError> {
     // 15 Spring Cleaning
     // - let poll = POLLS.may_load(deps.storage, poll_id.clone())?;
     // + let poll = POLLS.may_load(deps.storage, &poll_id)?;
-    let poll = POLLS.may_load(deps.storage, &poll_id)?;
-
-    match poll {
-        Some(mut poll) => {
-            // The poll exists
-            BALLOTS.update(
-                deps.storage,
-                // 15 Spring Cleaning
-                // - (info.sender, poll_id.clone()),
-                // + (info.sender, &poll_id)
-                (info.sender, &poll_id),
-                |ballot| -> StdResult<Ballot> {
-                    match ballot {
-                        Some(ballot) => {
-                            // We need to revoke their old vote
-                            // Find the position
-                            let position_of_old_vote = poll
-                                .options
-                                .iter()
-                                .position(|option| option.0 == ballot.option)
-                                .unwrap();
-                            // Decrement by 1
-                            poll.options[position_of_old_vote].1 -= 1;
-                            // Update the ballot
-                            Ok(Ballot {
-                                option: vote.clone(),
-                            })
-                        }
-                        None => {
-                            // Simply add the ballot
-                            Ok(Ballot {
-                                option: vote.clone(),
-                            })
-                        }
-                    }
-                },
+    let poll = polls().may_load(deps.storage, &poll_id)?;
+    let poll = match poll {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound { poll_id }), // The poll does not exist so we just error
+    };
+
+    let token_ids = token_ids.unwrap_or_default();
+    let mut voted_tokens = vec![];
+
+    // Voters vote with their weight in the merkle tree or else their member weight,
+    // unless they deposit. Deposits are held by the contract and refunded on a revote
+    // or once withdrawn.
+    let merkle_weight = merkle_weight(&poll, &info.sender, merkle_proof)?;
+    let config = CONFIG.load(deps.storage)?;
+    let member_weight = member_weight(
+        deps.as_ref(),
+        &config,
+        &info.sender,
+        Some(poll.snapshot_height),
+    )?;
+    let (weight, deposit) = match &poll.vote_weight {
+        VoteWeight::Equal => {
+            nonpayable(&info)?;
+            let weight = merkle_weight.unwrap_or_else(|| member_weight.unwrap_or(1).into());
+            (weight, Uint128::zero())
+        }
+        VoteWeight::Deposit { denom } => {
+            let amount = must_pay(&info, denom)?;
+            (amount, amount)
+        }
+        VoteWeight::Cw20 { .. } => return Err(ContractError::Cw20VoteRequired {}),
+        VoteWeight::Cw721 { address, per_token } => {
+            nonpayable(&info)?;
+            // Holders vote with one of their tokens, picked by the contract
+            voted_tokens = if *per_token {
+                token_ids
+            } else {
+                vec![nft_holder_token(
+                    deps.as_ref(),
+                    address,
+                    &info.sender,
+                    &poll_id,
+                )?]
+            };
+            let weight = nft_votes(
+                deps.as_ref(),
+                address,
+                &info.sender,
+                &poll_id,
+                &voted_tokens,
             )?;
+            (Uint128::from(weight), Uint128::zero())
+        }
+        VoteWeight::Staked => {
+            nonpayable(&info)?;
+            (staked_weight(deps.as_ref(), &info.sender)?, Uint128::zero())
+        }
+    };
 
-            // Find the position of the new vote option and increment it by 1
-            let position = poll.options.iter().position(|option| option.0 == vote);
-            if position.is_none() {
-                return Err(ContractError::Unauthorized {});
-            }
-            let position = position.unwrap();
-            poll.options[position].1 += 1;
+    cast_vote(
+        deps,
+        env,
+        info.sender,
+        poll_id,
+        poll,
+        vote,
+        weight,
+        deposit,
+        voted_tokens,
+    )
+}
 
-            // Save the update
-            // 15 Spring Cleaning
-            // - POLLS.save(deps.storage, poll_id, &poll)?;
-            // + POLLS.save(deps.storage, &poll_id, &poll)?;
-            POLLS.save(deps.storage, &poll_id, &poll)?;
-            Ok(Response::new())
+fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    // The sender is the token contract, which has to be the token of the poll.
+    // The whitelist is only checked when polls are created, so changing it does not
+    // lock out polls which are still open.
+    let config = CONFIG.load(deps.storage)?;
+    let voter = deps.api.addr_validate(&wrapper.sender)?;
+    match from_binary(&wrapper.msg)? {
+        ReceiveMsg::Vote {
+            poll_id,
+            vote,
+            merkle_proof,
+        } => {
+            let poll = match polls().may_load(deps.storage, &poll_id)? {
+                Some(poll) => poll,
+                None => return Err(ContractError::PollNotFound { poll_id }),
+            };
+            member_weight(deps.as_ref(), &config, &voter, Some(poll.snapshot_height))?;
+            merkle_weight(&poll, &voter, merkle_proof)?;
+            if poll.vote_weight
+                != (VoteWeight::Cw20 {
+                    address: info.sender,
+                })
+            {
+                return Err(ContractError::TokenNotAccepted {});
+            }
+            if wrapper.amount.is_zero() {
+                return Err(PaymentError::NoFunds {}.into());
+            }
+            cast_vote(
+                deps,
+                env,
+                voter,
+                poll_id,
+                poll,
+                vote,
+                wrapper.amount,
+                wrapper.amount,
+                vec![],
+            )
         }
-        None => Err(ContractError::Unauthorized {}), // The poll does not exist so we just error
     }
 }
 
-// 13 Query
-// - #[cfg_attr(not(feature = "library"), entry_point)]
-// - pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<Binary> {
-// -     unimplemented!()
-// - }
-// + #[cfg_attr(not(feature = "library"), entry_point)]
-// + pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-// +     match msg {
-// +         QueryMsg::AllPolls {} => query_all_polls(deps, env),
-// +         QueryMsg::Poll { poll_id } => query_poll(deps, env, poll_id),
-// +         QueryMsg::Vote { address, poll_id } => query_vote(deps, env, address, poll_id),
-// +     }
-// + }
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::AllPolls {} => query_all_polls(deps, env),
-        QueryMsg::Poll { poll_id } => query_poll(deps, env, poll_id),
-        QueryMsg::Vote { address, poll_id } => query_vote(deps, env, address, poll_id),
+// The weight of a member of the group at the given height, or the current weight without one.
+// None when there is no group, fails for addresses which are not a member.
+fn member_weight(
+    deps: Deps,
+    config: &Config,
+    addr: &Addr,
+    height: Option<u64>,
+) -> Result<Option<u64>, ContractError> {
+    let group_addr = match &config.group_addr {
+        Some(group_addr) => group_addr,
+        None => return Ok(None),
+    };
+    match Cw4Contract::new(group_addr.clone()).is_member(&deps.querier, addr, height)? {
+        Some(weight) => Ok(Some(weight)),
+        None => Err(ContractError::NotMember {}),
     }
 }
 
-// 13 Query
-// + fn query_all_polls(deps: Deps, _env: Env) -> StdResult<Binary> {
-// +     let polls = POLLS
-// +         .range(deps.storage, None, None, Order::Ascending)
-// +         .map(|p| Ok(p?.1))
-// +         .collect::<StdResult<Vec<_>>>()?;
-// +
-// +    to_binary(&AllPollsResponse { polls })
-// + }
-fn query_all_polls(deps: Deps, _env: Env) -> StdResult<Binary> {
-    let polls = POLLS
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|p| Ok(p?.1))
-        .collect::<StdResult<Vec<_>>>()?;
+// The weight in the voter's leaf of the merkle tree of the poll, None when the poll has
+// no merkle root or the leaf has no weight. Fails unless the proof leads to the root.
+fn merkle_weight(
+    poll: &Poll,
+    voter: &Addr,
+    merkle_proof: Option<MerkleProof>,
+) -> Result<Option<Uint128>, ContractError> {
+    let merkle_root = match &poll.merkle_root {
+        Some(merkle_root) => merkle_root,
+        None => return Ok(None),
+    };
+    let merkle_proof = match merkle_proof {
+        Some(merkle_proof) => merkle_proof,
+        None => return Err(ContractError::MissingProof {}),
+    };
 
-    to_binary(&AllPollsResponse { polls })
+    let mut hash = merkle_leaf(voter.as_str(), merkle_proof.weight);
+    for sibling in &merkle_proof.proof {
+        let mut sibling_hash = [0u8; 32];
+        if hex::decode_to_slice(sibling, &mut sibling_hash).is_err() {
+            return Err(ContractError::InvalidProof {});
+        }
+        hash = merkle_parent(hash, sibling_hash);
+    }
+    if &hex::encode(hash) != merkle_root {
+        return Err(ContractError::InvalidProof {});
+    }
+    Ok(merkle_proof.weight)
 }
 
-// 13 Query
-// + fn query_poll(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
-// +     let poll = POLLS.may_load(deps.storage, poll_id)?;
-// +     to_binary(&PollResponse { poll })
-// + }
-fn query_poll(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
-    // 15 Spring Cleaning
-    // - let poll = POLLS.may_load(deps.storage, poll_id)?;
-    // + let poll = POLLS.may_load(deps.storage, &poll_id)?;
-    let poll = POLLS.may_load(deps.storage, &poll_id)?;
-    to_binary(&PollResponse { poll })
+// Checks every token is owned by the voter and has not voted for anyone else.
+// Returns the number of votes, the tokens are recorded once the ballot is cast.
+fn nft_votes(
+    deps: Deps,
+    collection: &Addr,
+    voter: &Addr,
+    poll_id: &str,
+    token_ids: &[String],
+) -> Result<u64, ContractError> {
+    if token_ids.is_empty() {
+        return Err(ContractError::NoTokens {});
+    }
+    for (i, token_id) in token_ids.iter().enumerate() {
+        if token_ids[..i].contains(token_id) {
+            return Err(ContractError::DuplicateTokenId {
+                token_id: token_id.clone(),
+            });
+        }
+        let res: OwnerOfResponse = deps.querier.query_wasm_smart(
+            collection,
+            &Cw721QueryMsg::OwnerOf {
+                token_id: token_id.clone(),
+                include_expired: None,
+            },
+        )?;
+        if res.owner != voter.as_str() {
+            return Err(ContractError::NotTokenOwner {
+                token_id: token_id.clone(),
+            });
+        }
+        // A voter can reuse their own tokens when revoting
+        if let Some(previous_voter) = NFT_VOTES.may_load(deps.storage, (poll_id, token_id))? {
+            if &previous_voter != voter {
+                return Err(ContractError::TokenAlreadyVoted {
+                    token_id: token_id.clone(),
+                });
+            }
+        }
+    }
+    Ok(token_ids.len() as u64)
 }
 
-// 13 Query
-// + fn query_vote(deps: Deps, _env: Env, address: String, poll_id: String) -> StdResult<Binary> {
-// +     let validated_address = deps.api.addr_validate(&address).unwrap();
-// +     let vote = BALLOTS.may_load(deps.storage, (validated_address, poll_id))?;
-// +
-// +     to_binary(&VoteResponse { vote })
-// + }
-fn query_vote(deps: Deps, _env: Env, address: String, poll_id: String) -> StdResult<Binary> {
-    let validated_address = deps.api.addr_validate(&address).unwrap();
-    // 15 Spring Cleaning
-    // - let vote = BALLOTS.may_load(deps.storage, (validated_address, poll_id))?;
-    // + let vote = BALLOTS.may_load(deps.storage, (validated_address, &poll_id))?;
-    let vote = BALLOTS.may_load(deps.storage, (validated_address, &poll_id))?;
-
-    to_binary(&VoteResponse { vote })
+// The first token of the collection held by the voter which has not voted for anyone
+// else on the poll. Fails for addresses holding no tokens.
+fn nft_holder_token(
+    deps: Deps,
+    collection: &Addr,
+    voter: &Addr,
+    poll_id: &str,
+) -> Result<String, ContractError> {
+    let mut start_after = None;
+    loop {
+        let res: TokensResponse = deps.querier.query_wasm_smart(
+            collection,
+            &Cw721QueryMsg::Tokens {
+                owner: voter.to_string(),
+                start_after: start_after.clone(),
+                limit: Some(MAX_LIMIT),
+            },
+        )?;
+        let last = match res.tokens.last() {
+            Some(last) => last.clone(),
+            None if start_after.is_none() => return Err(ContractError::NotTokenHolder {}),
+            None => return Err(ContractError::NoUnusedTokens {}),
+        };
+        for token_id in res.tokens {
+            match NFT_VOTES.may_load(deps.storage, (poll_id, &token_id))? {
+                Some(previous_voter) if &previous_voter != voter => {}
+                _ => return Ok(token_id),
+            }
+        }
+        start_after = Some(last);
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    // 07 Instantiate Test
-    // + use cosmwasm_std::attr; // helper to construct an attribute e.g. ("action", "instantiate")
-    // + use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info}; // mock functions to mock an environment, message info, dependencies
-    // + use crate::contract::instantiate; // the contract instantiate function
-    // + use crate::msg::InstantiateMsg; // our instantate method
-
-    // + // Two fake addresses we will use to mock_info
-    // + pub const ADDR1: &str = "addr1";
-    // + pub const ADDR2: &str = "addr2";
+// The total stake the address has delegated across all validators
+fn staked_weight(deps: Deps, addr: &Addr) -> Result<Uint128, ContractError> {
+    let mut stake = Uint128::zero();
+    for delegation in deps.querier.query_all_delegations(addr)? {
+        stake = stake.checked_add(delegation.amount.amount)?;
+    }
+    if stake.is_zero() {
+        return Err(ContractError::NoStake {});
+    }
+    Ok(stake)
+}
 
-    // 11 Execute Tests
-    // - use crate::contract::instantiate; // the contract instantiate function
-    // - use crate::msg::InstantiateMsg;
-    // + use crate::contract::{instantiate, execute};
-    // + use crate::msg::{InstantiateMsg, ExecuteMsg};
+// Records the ballot of voter once the weight of their vote is known
+#[allow(clippy::too_many_arguments)]
+fn cast_vote(
+    deps: DepsMut,
+    env: Env,
+    voter: Addr,
+    poll_id: String,
+    mut poll: Poll,
+    vote: Choice,
+    weight: Uint128,
+    deposit: Uint128,
+    token_ids: Vec<String>,
+) -> Result<Response, ContractError> {
+    // The poll exists, but only accepts votes while open
+    if poll.status != PollStatus::Open {
+        return Err(ContractError::PollClosed {});
+    }
+    if poll.allowlist && !POLL_VOTERS.has(deps.storage, (&poll_id, &voter)) {
+        return Err(ContractError::VoterNotAllowed {});
+    }
+    if let Some(start) = poll.start {
+        if !start.is_expired(&env.block) {
+            return Err(ContractError::PollNotStarted {});
+        }
+    }
+    if let Some(end) = poll.end {
+        if end.is_expired(&env.block) {
+            return Err(ContractError::PollExpired {});
+        }
+    }
 
-    // 14 Query Tests
-    // - use crate::contract::{execute, instantiate};
-    // - use crate::msg::{ExecuteMsg, InstantiateMsg};
-    // + use crate::contract::{execute, instantiate, query};
-    // + use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, AllPollsResponse, PollResponse, VoteResponse};
-    use crate::contract::{execute, instantiate, query};
-    use crate::msg::{
-        AllPollsResponse, ExecuteMsg, InstantiateMsg, PollResponse, QueryMsg, VoteResponse,
+    let votes = match vote {
+        Choice::Single(option) => vec![(option, 1)],
+        Choice::Multiple(options) => options.into_iter().map(|option| (option, 1)).collect(),
+        Choice::Allocation(votes) => votes,
     };
-    // 14 Query Tests
-    // - use cosmwasm_std::attr;
-    // + use cosmwasm_std::{attr, from_binary};
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{attr, from_binary}; // helper to construct an attribute e.g. ("action", "instantiate") // mock functions to mock an environment, message info, dependencies // our instantate method
+    // Ranked choice ballots may rank every option, but only their first preference is
+    // counted in the poll. The full rankings are evaluated by the RankedResult query.
+    let (max_choices, counted) = match poll.voting_mode {
+        VotingMode::SingleChoice => (1, 1),
+        VotingMode::Approval { max_choices } => (max_choices, max_choices),
+        VotingMode::RankedChoice => (poll.options.len() as u32, 1),
+        VotingMode::Quadratic { .. } => (poll.options.len() as u32, poll.options.len() as u32),
+    };
+    if votes.is_empty() {
+        return Err(ContractError::NoChoices {});
+    }
+    if votes.len() > max_choices as usize {
+        return Err(ContractError::TooManyChoices { max_choices });
+    }
 
-    // Two fake addresses we will use to mock_info
-    pub const ADDR1: &str = "addr1";
-    pub const ADDR2: &str = "addr2";
+    // Find the positions of the new vote options before changing anything
+    let mut positions = vec![];
+    for (i, (choice, count)) in votes.iter().enumerate() {
+        // An option can only be counted once per ballot
+        if votes[..i].iter().any(|vote| &vote.0 == choice) {
+            return Err(ContractError::DuplicateOption {
+                option: choice.clone(),
+            });
+        }
+        let valid_count = match poll.voting_mode {
+            VotingMode::Quadratic { .. } => *count > 0,
+            _ => *count == 1,
+        };
+        if !valid_count {
+            return Err(ContractError::InvalidAllocation {});
+        }
+        match poll.options.iter().position(|option| &option.0 == choice) {
+            Some(position) => positions.push(position),
+            None => {
+                return Err(ContractError::InvalidOption {
+                    option: choice.clone(),
+                })
+            }
+        }
+    }
 
-    // 07 Instantiate Test
-    // + #[test]
-    // + fn test_instantiate() {
-    // +     // Mock the dependencies, must be mutable so we can pass it as a mutable, empty vector means our contract has no balance
-    // +     let mut deps = mock_dependencies();
-    // +     // Mock the contract environment, contains the block info, contract address, etc.
-    // +     let env = mock_env();
-    // +     // Mock the message info, ADDR1 will be the sender, the empty vec means we sent no funds.
-    // +     let info = mock_info(ADDR1, &vec![]);
-    // +
-    // +     // Create a message where we (the sender) will be an admin
-    // +     let msg = InstantiateMsg { admin: None };
-    // +     // Call instantiate, unwrap to assert success
-    // +     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
-    // +
-    // +     assert_eq!(
-    // +         res.attributes,
-    // +         vec![attr("action", "instantiate"), attr("admin", ADDR1)]
-    // +     )
-    // + }
-    #[test]
-    fn test_instantiate() {
-        // Mock the dependencies, must be mutable so we can pass it as a mutable, empty vector means our contract has no balance
-        let mut deps = mock_dependencies();
-        // Mock the contract environment, contains the block info, contract address, etc.
-        let env = mock_env();
-        // Mock the message info, ADDR1 will be the sender, the empty vec means we sent no funds.
-        let info = mock_info(ADDR1, &[]);
+    // Quadratic ballots add their votes, every other ballot adds its weight
+    let allocation: Vec<(String, Uint128)> = match poll.voting_mode {
+        VotingMode::Quadratic { budget } => {
+            let credits = budget.unwrap_or(weight);
+            let mut cost = Uint128::zero();
+            for (_, count) in &votes {
+                let count = Uint128::from(*count);
+                cost = cost.checked_add(count.checked_mul(count)?)?;
+            }
+            if cost > credits {
+                return Err(ContractError::NotEnoughCredits { credits });
+            }
+            votes
+                .iter()
+                .map(|(option, count)| (option.clone(), Uint128::from(*count)))
+                .collect()
+        }
+        _ => votes
+            .iter()
+            .take(counted as usize)
+            .map(|(option, _)| (option.clone(), weight))
+            .collect(),
+    };
 
-        // Create a message where we (the sender) will be an admin
-        let msg = InstantiateMsg { admin: None };
-        // Call instantiate, unwrap to assert success
-        let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    let mut response = Response::new();
+    let ballot = ballots().may_load(deps.storage, (voter.clone(), &poll_id))?;
+    if let Some(ballot) = ballot {
+        // We need to revoke everything their old vote added
+        if poll.voting_mode == VotingMode::RankedChoice {
+            let ranking = ranking_key(&poll, &ballot.options);
+            let total = RANKED_VOTES
+                .load(deps.storage, (&poll_id, &ranking))?
+                .checked_sub(ballot.weight)?;
+            if total.is_zero() {
+                RANKED_VOTES.remove(deps.storage, (&poll_id, &ranking));
+            } else {
+                RANKED_VOTES.save(deps.storage, (&poll_id, &ranking), &total)?;
+            }
+        }
+        for (old_choice, old_count) in ballot.allocation {
+            let position_of_old_vote = match poll
+                .options
+                .iter()
+                .position(|option| option.0 == old_choice)
+            {
+                Some(position) => position,
+                None => return Err(ContractError::InvalidOption { option: old_choice }),
+            };
+            poll.options[position_of_old_vote].1 = poll.options[position_of_old_vote]
+                .1
+                .checked_sub(old_count)?;
+        }
+        // The old deposit is refunded, the new one replaces it
+        if let Some(msg) = refund(&poll, &voter, ballot.deposit)? {
+            response = response.add_message(msg);
+        }
+        // Tokens the voter no longer votes with are free to vote for their owner again
+        for token_id in ballot.token_ids {
+            if !token_ids.contains(&token_id) {
+                NFT_VOTES.remove(deps.storage, (&poll_id, &token_id));
+            }
+        }
+    }
+    for token_id in &token_ids {
+        NFT_VOTES.save(deps.storage, (&poll_id, token_id), &voter)?;
+    }
+    for (position, (_, count)) in positions.into_iter().zip(&allocation) {
+        poll.options[position].1 = poll.options[position].1.checked_add(*count)?;
+    }
 
-        assert_eq!(
-            res.attributes,
-            vec![attr("action", "instantiate"), attr("admin", ADDR1)]
-        )
+    // Rankings are tallied as votes come in, so the runoff does not have to read every ballot
+    let options: Vec<String> = votes.into_iter().map(|(option, _)| option).collect();
+    if poll.voting_mode == VotingMode::RankedChoice {
+        let ranking = ranking_key(&poll, &options);
+        RANKED_VOTES.update(
+            deps.storage,
+            (&poll_id, &ranking),
+            |total| -> Result<_, ContractError> {
+                Ok(total.unwrap_or_default().checked_add(weight)?)
+            },
+        )?;
     }
 
-    // 07 Instantiate Test Exercise Solution
-    // + #[test]
+    // Save the update
+    let ballot = Ballot {
+        poll_id: poll_id.clone(),
+        options,
+        allocation,
+        weight,
+        deposit,
+        token_ids,
+    };
+    ballots().save(deps.storage, (voter, &poll_id), &ballot)?;
+    polls().save(deps.storage, &poll_id, &poll)?;
+    Ok(response)
+}
+
+// The positions of the ranked options in the poll, joined by commas
+fn ranking_key(poll: &Poll, ranking: &[String]) -> String {
+    ranking
+        .iter()
+        .filter_map(|choice| poll.options.iter().position(|option| &option.0 == choice))
+        .map(|position| position.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// The message returning a deposit of the poll, None when there is nothing to return
+fn refund(poll: &Poll, to: &Addr, amount: Uint128) -> StdResult<Option<CosmosMsg>> {
+    if amount.is_zero() {
+        return Ok(None);
+    }
+    let msg = match &poll.vote_weight {
+        VoteWeight::Equal | VoteWeight::Cw721 { .. } | VoteWeight::Staked => return Ok(None),
+        VoteWeight::Deposit { denom } => BankMsg::Send {
+            to_address: to.to_string(),
+            amount: coins(amount.u128(), denom),
+        }
+        .into(),
+        VoteWeight::Cw20 { address } => WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: to.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+    };
+    Ok(Some(msg))
+}
+
+fn execute_close_poll(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let mut poll = match polls().may_load(deps.storage, &poll_id)? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound { poll_id }),
+    };
+    let config = CONFIG.load(deps.storage)?;
+
+    // Only the creator of the poll or the contract admin can close it
+    if info.sender != poll.creator && config.admin.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if poll.status != PollStatus::Open {
+        return Err(ContractError::PollClosed {});
+    }
+
+    poll.status = PollStatus::Closed;
+    polls().save(deps.storage, &poll_id, &poll)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "close_poll")
+        .add_attribute("poll_id", poll_id))
+}
+
+fn execute_finalize_poll(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let mut poll = match polls().may_load(deps.storage, &poll_id)? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound { poll_id }),
+    };
+    if poll.status == PollStatus::Finalized {
+        return Err(ContractError::PollFinalized {});
+    }
+    if poll.status == PollStatus::Open && !poll_ended(&poll, &env) {
+        return Err(ContractError::PollNotEnded {});
+    }
+
+    poll.status = PollStatus::Finalized;
+    polls().save(deps.storage, &poll_id, &poll)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "finalize_poll")
+        .add_attribute("poll_id", poll_id))
+}
+
+// Whether the end of the poll has been reached
+fn poll_ended(poll: &Poll, env: &Env) -> bool {
+    match poll.end {
+        Some(end) => end.is_expired(&env.block),
+        None => false,
+    }
+}
+
+fn execute_update_voters(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    voters: Vec<String>,
+    allowed: bool,
+) -> Result<Response, ContractError> {
+    let poll = match polls().may_load(deps.storage, &poll_id)? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound { poll_id }),
+    };
+    if info.sender != poll.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !poll.allowlist {
+        return Err(ContractError::NoAllowlist {});
+    }
+
+    // Removed voters keep their ballot, they can only no longer change it
+    for voter in voters {
+        let voter = deps.api.addr_validate(&voter)?;
+        if allowed {
+            POLL_VOTERS.save(deps.storage, (&poll_id, &voter), &())?;
+        } else {
+            POLL_VOTERS.remove(deps.storage, (&poll_id, &voter));
+        }
+    }
+
+    let action = if allowed {
+        "add_voters"
+    } else {
+        "remove_voters"
+    };
+    Ok(Response::new()
+        .add_attribute("action", action)
+        .add_attribute("poll_id", poll_id))
+}
+
+fn execute_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let poll = match polls().may_load(deps.storage, &poll_id)? {
+        Some(poll) => poll,
+        None => return Err(ContractError::PollNotFound { poll_id }),
+    };
+
+    // Deposits stay locked while they still count towards the tally
+    if poll.status == PollStatus::Open && !poll_ended(&poll, &env) {
+        return Err(ContractError::PollStillOpen {});
+    }
+
+    let mut ballot = match ballots().may_load(deps.storage, (info.sender.clone(), &poll_id))? {
+        Some(ballot) => ballot,
+        None => return Err(ContractError::NothingToWithdraw {}),
+    };
+    let msg = match refund(&poll, &info.sender, ballot.deposit)? {
+        Some(msg) => msg,
+        None => return Err(ContractError::NothingToWithdraw {}),
+    };
+
+    // The ballot keeps its weight, only the deposit is returned
+    ballot.deposit = Uint128::zero();
+    ballots().save(deps.storage, (info.sender, &poll_id), &ballot)?;
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("action", "withdraw")
+        .add_attribute("poll_id", poll_id))
+}
+
+fn execute_propose_new_admin(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    new_admin: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.admin != Some(info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Proposing again replaces the previous proposal, so a typo can be corrected
+    let new_admin = deps.api.addr_validate(&new_admin)?;
+    PENDING_ADMIN.save(deps.storage, &new_admin)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_new_admin")
+        .add_attribute("pending_admin", new_admin))
+}
+
+fn execute_accept_admin(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending_admin = match PENDING_ADMIN.may_load(deps.storage)? {
+        Some(pending_admin) => pending_admin,
+        None => return Err(ContractError::NoPendingAdmin {}),
+    };
+    if info.sender != pending_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
+        config.admin = Some(pending_admin.clone());
+        Ok(config)
+    })?;
+    PENDING_ADMIN.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_admin")
+        .add_attribute("admin", pending_admin))
+}
+
+fn execute_renounce_admin(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.admin != Some(info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.admin = None;
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_ADMIN.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "renounce_admin"))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_update_config(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_options: Option<u32>,
+    max_question_len: Option<u32>,
+    max_option_len: Option<u32>,
+    max_poll_id_len: Option<u32>,
+    cw20_addr: Option<String>,
+    remove_cw20: Option<bool>,
+    group_addr: Option<String>,
+    remove_group: Option<bool>,
+    members_only_create: Option<bool>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.admin != Some(info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(max_options) = max_options {
+        config.max_options = max_options;
+    }
+    if let Some(max_question_len) = max_question_len {
+        config.max_question_len = max_question_len;
+    }
+    if let Some(max_option_len) = max_option_len {
+        config.max_option_len = max_option_len;
+    }
+    if let Some(max_poll_id_len) = max_poll_id_len {
+        config.max_poll_id_len = max_poll_id_len;
+    }
+    if remove_cw20 == Some(true) {
+        config.cw20_addr = None;
+    } else if let Some(cw20_addr) = cw20_addr {
+        config.cw20_addr = Some(deps.api.addr_validate(&cw20_addr)?);
+    }
+    if remove_group == Some(true) {
+        config.group_addr = None;
+    } else if let Some(group_addr) = group_addr {
+        config.group_addr = Some(deps.api.addr_validate(&group_addr)?);
+    }
+    if let Some(members_only_create) = members_only_create {
+        config.members_only_create = members_only_create;
+    }
+    validate_config(&config)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "update_config"))
+}
+
+// Refuses limits under which every new poll would fail, and restricting poll creation
+// to members when there is no group
+fn validate_config(config: &Config) -> Result<(), ContractError> {
+    if config.max_options < 2
+        || config.max_question_len == 0
+        || config.max_option_len == 0
+        || config.max_poll_id_len == 0
+    {
+        return Err(ContractError::InvalidConfig {});
+    }
+    if config.members_only_create && config.group_addr.is_none() {
+        return Err(ContractError::NoGroup {});
+    }
+    Ok(())
+}
+
+// 13 Query
+// - #[cfg_attr(not(feature = "library"), entry_point)]
+// - pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<Binary> {
+// -     unimplemented!()
+// - }
+// + #[cfg_attr(not(feature = "library"), entry_point)]
+// + pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+// +     match msg {
+// +         QueryMsg::AllPolls {} => query_all_polls(deps, env),
+// +         QueryMsg::Poll { poll_id } => query_poll(deps, env, poll_id),
+// +         QueryMsg::Vote { address, poll_id } => query_vote(deps, env, address, poll_id),
+// +     }
+// + }
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => query_config(deps, env),
+        QueryMsg::AllPolls { start_after, limit } => query_all_polls(deps, env, start_after, limit),
+        QueryMsg::PollsByCreator {
+            creator,
+            start_after,
+            limit,
+        } => query_polls_by_creator(deps, env, creator, start_after, limit),
+        QueryMsg::Poll { poll_id } => query_poll(deps, env, poll_id),
+        QueryMsg::Vote { address, poll_id } => query_vote(deps, env, address, poll_id),
+        QueryMsg::ListVotes {
+            poll_id,
+            start_after,
+            limit,
+        } => query_list_votes(deps, env, poll_id, start_after, limit),
+        QueryMsg::VotesByVoter {
+            address,
+            start_after,
+            limit,
+        } => query_votes_by_voter(deps, env, address, start_after, limit),
+        QueryMsg::RankedResult { poll_id } => query_ranked_result(deps, env, poll_id),
+    }
+}
+
+// 13 Query
+// + fn query_all_polls(deps: Deps, _env: Env) -> StdResult<Binary> {
+// +     let polls = POLLS
+// +         .range(deps.storage, None, None, Order::Ascending)
+// +         .map(|p| Ok(p?.1))
+// +         .collect::<StdResult<Vec<_>>>()?;
+// +
+// +    to_binary(&AllPollsResponse { polls })
+// + }
+fn query_all_polls(
+    deps: Deps,
+    _env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+    let polls = polls()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&AllPollsResponse { polls })
+}
+
+fn query_polls_by_creator(
+    deps: Deps,
+    _env: Env,
+    creator: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let creator = deps.api.addr_validate(&creator)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let polls = polls()
+        .idx
+        .creator
+        .prefix(creator)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&AllPollsResponse { polls })
+}
+
+// 13 Query
+// + fn query_poll(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
+// +     let poll = POLLS.may_load(deps.storage, poll_id)?;
+// +     to_binary(&PollResponse { poll })
+// + }
+fn query_poll(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
+    // 15 Spring Cleaning
+    // - let poll = POLLS.may_load(deps.storage, poll_id)?;
+    // + let poll = POLLS.may_load(deps.storage, &poll_id)?;
+    let poll = polls().may_load(deps.storage, &poll_id)?;
+    to_binary(&PollResponse { poll })
+}
+
+// 13 Query
+// + fn query_vote(deps: Deps, _env: Env, address: String, poll_id: String) -> StdResult<Binary> {
+// +     let validated_address = deps.api.addr_validate(&address).unwrap();
+// +     let vote = BALLOTS.may_load(deps.storage, (validated_address, poll_id))?;
+// +
+// +     to_binary(&VoteResponse { vote })
+// + }
+fn query_vote(deps: Deps, _env: Env, address: String, poll_id: String) -> StdResult<Binary> {
+    let validated_address = deps.api.addr_validate(&address)?;
+    // 15 Spring Cleaning
+    // - let vote = BALLOTS.may_load(deps.storage, (validated_address, poll_id))?;
+    // + let vote = BALLOTS.may_load(deps.storage, (validated_address, &poll_id))?;
+    let vote = ballots().may_load(deps.storage, (validated_address, &poll_id))?;
+
+    to_binary(&VoteResponse { vote })
+}
+
+fn query_list_votes(
+    deps: Deps,
+    _env: Env,
+    poll_id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(voter) => {
+            let voter = deps.api.addr_validate(&voter)?;
+            Some(Bound::exclusive((voter, poll_id.clone())))
+        }
+        None => None,
+    };
+    let votes = ballots()
+        .idx
+        .poll
+        .prefix(poll_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|((voter, _), ballot)| (voter, ballot)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&ListVotesResponse { votes })
+}
+
+fn query_votes_by_voter(
+    deps: Deps,
+    _env: Env,
+    address: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let voter = deps.api.addr_validate(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+    let votes = ballots()
+        .prefix(voter)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&VotesByVoterResponse { votes })
+}
+
+fn query_ranked_result(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
+    let poll = polls().load(deps.storage, &poll_id)?;
+    if poll.voting_mode != VotingMode::RankedChoice {
+        return Err(StdError::generic_err(
+            "Poll does not use ranked choice voting",
+        ));
+    }
+    let options: Vec<String> = poll.options.into_iter().map(|option| option.0).collect();
+    let rankings = RANKED_VOTES
+        .prefix(&poll_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(ranking, weight)| {
+                let ranking = ranking
+                    .split(',')
+                    .filter_map(|position| position.parse::<usize>().ok())
+                    .filter_map(|position| options.get(position).cloned())
+                    .collect();
+                (ranking, weight)
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let (rounds, winner) = instant_runoff(options, &rankings);
+    to_binary(&RankedResultResponse { rounds, winner })
+}
+
+// Every round each ballot counts its weight for its most preferred option which is still
+// remaining. An option with more than half of that weight wins, otherwise the options with the fewest
+// votes are eliminated. When every remaining option is tied there is no winner.
+fn instant_runoff(
+    mut remaining: Vec<String>,
+    rankings: &[(Vec<String>, Uint128)],
+) -> (Vec<RankedRound>, Option<String>) {
+    let mut rounds = vec![];
+    while !remaining.is_empty() {
+        let mut counts: Vec<(String, Uint128)> = remaining
+            .iter()
+            .map(|option| (option.clone(), Uint128::zero()))
+            .collect();
+        let mut total = Uint128::zero();
+        for (ranking, weight) in rankings {
+            if let Some(choice) = ranking.iter().find(|choice| remaining.contains(choice)) {
+                if let Some(count) = counts.iter_mut().find(|count| &count.0 == choice) {
+                    count.1 += *weight;
+                    total += *weight;
+                }
+            }
+        }
+
+        if let Some(count) = counts.iter().find(|count| count.1 > total - count.1) {
+            let winner = count.0.clone();
+            rounds.push(RankedRound {
+                counts,
+                eliminated: vec![],
+            });
+            return (rounds, Some(winner));
+        }
+
+        let fewest = counts.iter().map(|count| count.1).min().unwrap_or_default();
+        let eliminated: Vec<String> = counts
+            .iter()
+            .filter(|count| count.1 == fewest)
+            .map(|count| count.0.clone())
+            .collect();
+        remaining.retain(|option| !eliminated.contains(option));
+        rounds.push(RankedRound { counts, eliminated });
+    }
+    (rounds, None)
+}
+
+fn query_config(deps: Deps, _env: Env) -> StdResult<Binary> {
+    let config = CONFIG.load(deps.storage)?;
+    let pending_admin = PENDING_ADMIN.may_load(deps.storage)?;
+
+    to_binary(&ConfigResponse {
+        admin: config.admin,
+        pending_admin,
+        max_options: config.max_options,
+        max_question_len: config.max_question_len,
+        max_option_len: config.max_option_len,
+        max_poll_id_len: config.max_poll_id_len,
+        cw20_addr: config.cw20_addr,
+        group_addr: config.group_addr,
+        members_only_create: config.members_only_create,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+
+    // Downgrades are refused, the state of a newer version may not be understood
+    let stored_version: Version = stored.version.parse()?;
+    let version: Version = CONTRACT_VERSION.parse()?;
+    if stored_version > version {
+        return Err(ContractError::CannotMigrateVersion {
+            previous_version: stored.version,
+        });
+    }
+
+    if stored_version < Version::new(0, 2, 0) {
+        migrate_from_v0_1(deps.storage, env.block.height)?;
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+// The config gained limits, a token and a group. Polls gained a status, schedule, voting mode,
+// vote weight, snapshot height, allowlist and merkle root and count in Uint128. Ballots gained their poll id,
+// a list of options, an allocation, a weight, a deposit and token ids. Both maps gained indexes.
+// Every entry is rewritten in the new layout, which also fills the indexes.
+// Migrated polls take their snapshot at the height of the migration, and the poll count
+// starts after the largest numeric id clients chose before ids were assigned.
+fn migrate_from_v0_1(storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+    let legacy_config = LEGACY_CONFIG.load(storage)?;
+    let config = Config {
+        admin: Some(legacy_config.admin),
+        max_options: DEFAULT_MAX_OPTIONS,
+        max_question_len: DEFAULT_MAX_QUESTION_LEN,
+        max_option_len: DEFAULT_MAX_OPTION_LEN,
+        max_poll_id_len: DEFAULT_MAX_POLL_ID_LEN,
+        cw20_addr: None,
+        group_addr: None,
+        members_only_create: false,
+    };
+    CONFIG.save(storage, &config)?;
+
+    let legacy_polls = LEGACY_POLLS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let count = legacy_polls
+        .iter()
+        .filter_map(|(poll_id, _)| poll_id.parse::<u64>().ok())
+        .max()
+        .unwrap_or_default();
+    POLL_COUNT.save(storage, &count)?;
+    for (poll_id, legacy_poll) in legacy_polls {
+        // Removed first, the indexed map would otherwise try to read the old layout
+        LEGACY_POLLS.remove(storage, &poll_id);
+        let poll = Poll {
+            creator: legacy_poll.creator,
+            question: legacy_poll.question,
+            options: legacy_poll
+                .options
+                .into_iter()
+                .map(|(option, count)| (option, Uint128::from(count)))
+                .collect(),
+            status: PollStatus::Open,
+            start: None,
+            end: None,
+            voting_mode: VotingMode::SingleChoice,
+            vote_weight: VoteWeight::Equal,
+            snapshot_height: height,
+            allowlist: false,
+            merkle_root: None,
+        };
+        polls().save(storage, &poll_id, &poll)?;
+    }
+
+    let legacy_ballots = LEGACY_BALLOTS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for ((voter, poll_id), legacy_ballot) in legacy_ballots {
+        LEGACY_BALLOTS.remove(storage, (voter.clone(), &poll_id));
+        let ballot = Ballot {
+            poll_id: poll_id.clone(),
+            options: vec![legacy_ballot.option.clone()],
+            allocation: vec![(legacy_ballot.option, Uint128::new(1))],
+            weight: Uint128::new(1),
+            deposit: Uint128::zero(),
+            token_ids: vec![],
+        };
+        ballots().save(storage, (voter, &poll_id), &ballot)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    // 07 Instantiate Test
+    // + use cosmwasm_std::attr; // helper to construct an attribute e.g. ("action", "instantiate")
+    // + use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info}; // mock functions to mock an environment, message info, dependencies
+    // + use crate::contract::instantiate; // the contract instantiate function
+    // + use crate::msg::InstantiateMsg; // our instantate method
+
+    // + // Two fake addresses we will use to mock_info
+    // + pub const ADDR1: &str = "addr1";
+    // + pub const ADDR2: &str = "addr2";
+
+    // 11 Execute Tests
+    // - use crate::contract::instantiate; // the contract instantiate function
+    // - use crate::msg::InstantiateMsg;
+    // + use crate::contract::{instantiate, execute};
+    // + use crate::msg::{InstantiateMsg, ExecuteMsg};
+
+    // 14 Query Tests
+    // - use crate::contract::{execute, instantiate};
+    // - use crate::msg::{ExecuteMsg, InstantiateMsg};
+    // + use crate::contract::{execute, instantiate, query};
+    // + use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, AllPollsResponse, PollResponse, VoteResponse};
+    use crate::contract::{execute, instantiate, migrate, query, CONTRACT_NAME};
+    use crate::helpers::{merkle_leaf, merkle_parent, merkle_tree, merkle_voters};
+    use crate::msg::{
+        AllPollsResponse, Choice, ConfigResponse, CreatePollResponse, ExecuteMsg, InstantiateMsg,
+        ListVotesResponse, MerkleProof, MigrateMsg, PollResponse, QueryMsg, RankedResultResponse,
+        ReceiveMsg, VoteResponse, VoteWeightMsg, VotesByVoterResponse,
+    };
+    use crate::state::{
+        ballots, Ballot, LegacyBallot, LegacyConfig, LegacyPoll, PollStatus, VotingMode,
+        LEGACY_BALLOTS, LEGACY_CONFIG, LEGACY_POLLS, NFT_VOTES, RANKED_VOTES,
+    };
+    use crate::ContractError;
+    use cosmwasm_std::{
+        coin, coins, to_binary, Addr, BankMsg, ContractResult, CosmosMsg, FullDelegation, Order,
+        QuerierResult, StdResult, Storage, SystemResult, Uint128, WasmMsg, WasmQuery,
+    };
+    use cw2::{get_contract_version, set_contract_version};
+    use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+    use cw4::Member;
+    use cw721::{Cw721QueryMsg, OwnerOfResponse, TokensResponse};
+    use cw_multi_test::{next_block, App, ContractWrapper, Executor};
+    use cw_utils::{Expiration, PaymentError};
+    // 14 Query Tests
+    // - use cosmwasm_std::attr;
+    // + use cosmwasm_std::{attr, from_binary};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{attr, from_binary}; // helper to construct an attribute e.g. ("action", "instantiate") // mock functions to mock an environment, message info, dependencies // our instantate method
+
+    // Two fake addresses we will use to mock_info
+    pub const ADDR1: &str = "addr1";
+    pub const ADDR2: &str = "addr2";
+
+    // The fields of a CreatePoll message, so tests only need to give the ones they use
+    #[derive(Default)]
+    struct NewPoll {
+        poll_id: Option<String>,
+        question: String,
+        options: Vec<String>,
+        start: Option<Expiration>,
+        end: Option<Expiration>,
+        voting_mode: Option<VotingMode>,
+        vote_weight: Option<VoteWeightMsg>,
+        voters: Option<Vec<String>>,
+        merkle_root: Option<String>,
+    }
+
+    impl NewPoll {
+        fn msg(self) -> ExecuteMsg {
+            ExecuteMsg::CreatePoll {
+                poll_id: self.poll_id,
+                question: self.question,
+                options: self.options,
+                start: self.start,
+                end: self.end,
+                voting_mode: self.voting_mode,
+                vote_weight: self.vote_weight,
+                voters: self.voters,
+                merkle_root: self.merkle_root,
+            }
+        }
+    }
+
+    // A vote without NFTs or a merkle proof
+    fn vote_msg(poll_id: &str, vote: Choice) -> ExecuteMsg {
+        ExecuteMsg::Vote {
+            poll_id: poll_id.to_string(),
+            vote,
+            token_ids: None,
+            merkle_proof: None,
+        }
+    }
+
+    // 07 Instantiate Test
+    // + #[test]
+    // + fn test_instantiate() {
+    // +     // Mock the dependencies, must be mutable so we can pass it as a mutable, empty vector means our contract has no balance
+    // +     let mut deps = mock_dependencies();
+    // +     // Mock the contract environment, contains the block info, contract address, etc.
+    // +     let env = mock_env();
+    // +     // Mock the message info, ADDR1 will be the sender, the empty vec means we sent no funds.
+    // +     let info = mock_info(ADDR1, &vec![]);
+    // +
+    // +     // Create a message where we (the sender) will be an admin
+    // +     let msg = InstantiateMsg { admin: None };
+    // +     // Call instantiate, unwrap to assert success
+    // +     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    // +
+    // +     assert_eq!(
+    // +         res.attributes,
+    // +         vec![attr("action", "instantiate"), attr("admin", ADDR1)]
+    // +     )
+    // + }
+    #[test]
+    fn test_instantiate() {
+        // Mock the dependencies, must be mutable so we can pass it as a mutable, empty vector means our contract has no balance
+        let mut deps = mock_dependencies();
+        // Mock the contract environment, contains the block info, contract address, etc.
+        let env = mock_env();
+        // Mock the message info, ADDR1 will be the sender, the empty vec means we sent no funds.
+        let info = mock_info(ADDR1, &[]);
+
+        // Create a message where we (the sender) will be an admin
+        let msg = InstantiateMsg::default();
+        // Call instantiate, unwrap to assert success
+        let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "instantiate"), attr("admin", ADDR1)]
+        )
+    }
+
+    // 07 Instantiate Test Exercise Solution
+    // + #[test]
     // + fn test_instantiate_with_admin() {
     // +     let mut deps = mock_dependencies();
     // +     let env = mock_env();
-    // +     // Send as ADDR1 to show admin is different
+    // +     // Send as ADDR1 to show admin is different
+    // +     let info = mock_info(ADDR1, &vec![]);
+    // +
+    // +     // Create a message where ADDR2 will be an admin
+    // +     // Have to use .to_string() method
+    // +     let msg = InstantiateMsg {
+    // +         admin: Some(ADDR2.to_string()),
+    // +     };
+    // +     // Unwrap to assert success
+    // +     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    // +     // Assert admin is ADDR2 instead
+    // +     assert_eq!(
+    // +         res.attributes,
+    // +         vec![attr("action", "instantiate"), attr("admin", ADDR2),]
+    // +     );
+    // + }
+    #[test]
+    fn test_instantiate_with_admin() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        // Send as ADDR1 to show admin is different
+        let info = mock_info(ADDR1, &[]);
+
+        // Create a message where ADDR2 will be an admin
+        // Have to use .to_string() method
+        let msg = InstantiateMsg {
+            admin: Some(ADDR2.to_string()),
+            ..Default::default()
+        };
+        // Unwrap to assert success
+        let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+        // Assert admin is ADDR2 instead
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "instantiate"), attr("admin", ADDR2),]
+        );
+    }
+
+    #[test]
+    fn test_execute_admin_transfer() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract with ADDR1 as admin
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Only the admin can propose a new admin
+        let msg = ExecuteMsg::ProposeNewAdmin {
+            new_admin: ADDR2.to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // The proposal is visible but ADDR1 is still the admin
+        let bin = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let res: ConfigResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.admin, Some(Addr::unchecked(ADDR1)));
+        assert_eq!(res.pending_admin, Some(Addr::unchecked(ADDR2)));
+
+        // Only the proposed admin can accept
+        let msg = ExecuteMsg::AcceptAdmin {};
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            msg.clone(),
+        )
+        .unwrap();
+
+        let bin = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let res: ConfigResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.admin, Some(Addr::unchecked(ADDR2)));
+        assert_eq!(res.pending_admin, None);
+
+        // The proposal cannot be accepted twice
+        let err = execute(deps.as_mut(), env.clone(), mock_info(ADDR2, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoPendingAdmin {});
+
+        // The new admin renounces, leaving the contract without an admin
+        let msg = ExecuteMsg::RenounceAdmin {};
+        let _res = execute(deps.as_mut(), env.clone(), mock_info(ADDR2, &[]), msg).unwrap();
+
+        let bin = query(deps.as_ref(), env, QueryMsg::Config {}).unwrap();
+        let res: ConfigResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.admin, None);
+    }
+
+    #[test]
+    fn test_execute_update_config() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Limits which no poll could meet are refused
+        let msg = InstantiateMsg {
+            max_poll_id_len: Some(0),
+            ..Default::default()
+        };
+        let err = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidConfig {});
+
+        // Instantiate the contract allowing at most 2 options
+        let msg = InstantiateMsg {
+            max_options: Some(2),
+            ..Default::default()
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your colour?".to_string(),
+            options: vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::TooManyOptions {});
+
+        // Only the admin can update the config
+        let update_msg = ExecuteMsg::UpdateConfig {
+            max_options: Some(3),
+            max_question_len: None,
+            max_option_len: Some(4),
+            max_poll_id_len: None,
+            cw20_addr: None,
+            remove_cw20: None,
+            group_addr: None,
+            remove_group: None,
+            members_only_create: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            update_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), update_msg).unwrap();
+
+        let bin = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let res: ConfigResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.max_options, 3);
+        assert_eq!(res.max_question_len, 256);
+        assert_eq!(res.max_option_len, 4);
+
+        let invalid_msg = ExecuteMsg::UpdateConfig {
+            max_options: Some(1),
+            max_question_len: None,
+            max_option_len: None,
+            max_poll_id_len: None,
+            cw20_addr: None,
+            remove_cw20: None,
+            group_addr: None,
+            remove_group: None,
+            members_only_create: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), invalid_msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidConfig {});
+
+        // Three options are allowed now, but "Green" is too long
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::OptionTooLong {
+                option: "Green".to_string()
+            }
+        );
+    }
+
+    // 11 Execute Tests
+    // + #[test]
+    // + fn test_execute_create_poll_valid() {
+    // +     let mut deps = mock_dependencies();
+    // +     let env = mock_env();
     // +     let info = mock_info(ADDR1, &vec![]);
+    // +     // Instantiate the contract
+    // +     let msg = InstantiateMsg { admin: None };
+    // +     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
     // +
-    // +     // Create a message where ADDR2 will be an admin
-    // +     // Have to use .to_string() method
-    // +     let msg = InstantiateMsg {
-    // +         admin: Some(ADDR2.to_string()),
+    // +     // New execute msg
+    // +     let msg = ExecuteMsg::CreatePoll {
+    // +         poll_id: "some_id".to_string(),
+    // +         question: "What's your favourite Cosmos coin?".to_string(),
+    // +         options: vec![
+    // +             "Cosmos Hub".to_string(),
+    // +             "Juno".to_string(),
+    // +             "Osmosis".to_string(),
+    // +         ],
     // +     };
+    // +
     // +     // Unwrap to assert success
-    // +     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
-    // +     // Assert admin is ADDR2 instead
-    // +     assert_eq!(
-    // +         res.attributes,
-    // +         vec![attr("action", "instantiate"), attr("admin", ADDR2),]
-    // +     );
+    // +     let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+    // + }
+    #[test]
+    fn test_execute_create_poll_valid() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // New execute msg
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            ..Default::default()
+        }
+        .msg();
+
+        // Unwrap to assert success
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+
+        // The same poll id cannot be used twice
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DuplicatePollId {
+                poll_id: "some_id".to_string()
+            }
+        );
+    }
+
+    // 11 Execute Tests
+    // + #[test]
+    // + fn test_execute_create_poll_invalid() {
+    // +     let mut deps = mock_dependencies();
+    // +     let env = mock_env();
+    // +     let info = mock_info(ADDR1, &vec![]);
+    // +     // Instantiate the contract
+    // +     let msg = InstantiateMsg { admin: None };
+    // +     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    // +
+    // +     let msg = ExecuteMsg::CreatePoll {
+    // +         poll_id: "some_id".to_string(),
+    // +         question: "What's your favourite number?".to_string(),
+    // +         options: vec![
+    // +             "1".to_string(),
+    // +             "2".to_string(),
+    // +             "3".to_string(),
+    // +             "4".to_string(),
+    // +             "5".to_string(),
+    // +             "6".to_string(),
+    // +             "7".to_string(),
+    // +             "8".to_string(),
+    // +             "9".to_string(),
+    // +             "10".to_string(),
+    // +             "11".to_string(),
+    // +         ],
+    // +     };
+    // +
+    // +     // Unwrap error to assert failure
+    // +     let _err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+    // + }
+    #[test]
+    fn test_execute_create_poll_invalid() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite number?".to_string(),
+            options: vec![
+                "1".to_string(),
+                "2".to_string(),
+                "3".to_string(),
+                "4".to_string(),
+                "5".to_string(),
+                "6".to_string(),
+                "7".to_string(),
+                "8".to_string(),
+                "9".to_string(),
+                "10".to_string(),
+                "11".to_string(),
+            ],
+            ..Default::default()
+        }
+        .msg();
+
+        // Unwrap error to assert failure
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(err, ContractError::TooManyOptions {});
+    }
+
+    #[test]
+    fn test_execute_create_poll_validation() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Each invalid poll paired with the error it should produce
+        let cases = vec![
+            (
+                "",
+                "What's your colour?",
+                vec!["Red", "Green"],
+                ContractError::EmptyPollId {},
+            ),
+            (
+                "some_id",
+                " ",
+                vec!["Red", "Green"],
+                ContractError::EmptyQuestion {},
+            ),
+            (
+                "some_id",
+                "What's your colour?",
+                vec![],
+                ContractError::TooFewOptions {},
+            ),
+            (
+                "some_id",
+                "What's your colour?",
+                vec!["Red"],
+                ContractError::TooFewOptions {},
+            ),
+            (
+                "some_id",
+                "What's your colour?",
+                vec!["Red", ""],
+                ContractError::EmptyOption {},
+            ),
+            (
+                "some_id",
+                "Do you agree?",
+                vec!["Yes", "No", "Yes"],
+                ContractError::DuplicateOption {
+                    option: "Yes".to_string(),
+                },
+            ),
+            (
+                "some_id",
+                "Do you agree?",
+                vec!["Yes", "No", " Yes "],
+                ContractError::DuplicateOption {
+                    option: "Yes".to_string(),
+                },
+            ),
+        ];
+        for (poll_id, question, options, expected) in cases {
+            let msg = NewPoll {
+                poll_id: Some(poll_id.to_string()),
+                question: question.to_string(),
+                options: options.into_iter().map(String::from).collect(),
+                ..Default::default()
+            }
+            .msg();
+            let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+            assert_eq!(err, expected);
+        }
+
+        // Deposits need a denom
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your colour?".to_string(),
+            options: vec!["Red".to_string(), "Green".to_string()],
+            vote_weight: Some(VoteWeightMsg::Deposit {
+                denom: " ".to_string(),
+            }),
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(err, ContractError::EmptyDenom {});
+    }
+
+    // 11 Execute Tests
+    // + #[test]
+    // + fn test_execute_vote_valid() {
+    // +     let mut deps = mock_dependencies();
+    // +     let env = mock_env();
+    // +     let info = mock_info(ADDR1, &vec![]);
+    // +     // Instantiate the contract
+    // +     let msg = InstantiateMsg { admin: None };
+    // +     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    // +
+    // +     // Create the poll
+    // +     let msg = ExecuteMsg::CreatePoll {
+    // +         poll_id: "some_id".to_string(),
+    // +         question: "What's your favourite Cosmos coin?".to_string(),
+    // +         options: vec![
+    // +             "Cosmos Hub".to_string(),
+    // +             "Juno".to_string(),
+    // +             "Osmosis".to_string(),
+    // +         ],
+    // +     };
+    // +     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    // +
+    // +     // Create the vote, first time voting
+    // +     let msg = ExecuteMsg::Vote {
+    // +         poll_id: "some_id".to_string(),
+    // +         vote: "Juno".to_string(),
+    // +     };
+    // +     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    // +
+    // +     // Change the vote
+    // +     let msg = ExecuteMsg::Vote {
+    // +         poll_id: "some_id".to_string(),
+    // +         vote: "Osmosis".to_string(),
+    // +     };
+    // +     let _res = execute(deps.as_mut(), env, info, msg).unwrap();
     // + }
     #[test]
-    fn test_instantiate_with_admin() {
+    fn test_execute_vote_valid() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create the poll
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create the vote, first time voting
+        let msg = vote_msg("some_id", Choice::Single("Juno".to_string()));
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Change the vote
+        let msg = vote_msg("some_id", Choice::Single("Osmosis".to_string()));
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+    }
+
+    #[test]
+    fn test_execute_vote_approval() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Allowing more choices than there are options is an error
+        let options = vec![
+            "Cosmos Hub".to_string(),
+            "Juno".to_string(),
+            "Osmosis".to_string(),
+        ];
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "Which Cosmos coins do you hold?".to_string(),
+            options: options.clone(),
+            voting_mode: Some(VotingMode::Approval { max_choices: 4 }),
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidMaxChoices {});
+
+        // Create an approval poll allowing two choices
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "Which Cosmos coins do you hold?".to_string(),
+            options,
+            voting_mode: Some(VotingMode::Approval { max_choices: 2 }),
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Vote for two options, sent the way a client would
+        let msg: ExecuteMsg =
+            from_binary(&br#"{"vote":{"poll_id":"some_id","vote":["Juno","Osmosis"]}}"#.into())
+                .unwrap();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0], ("Cosmos Hub".to_string(), Uint128::new(0)));
+        assert_eq!(poll.options[1], ("Juno".to_string(), Uint128::new(1)));
+        assert_eq!(poll.options[2], ("Osmosis".to_string(), Uint128::new(1)));
+
+        // Too many choices, no choices and repeated choices are all errors
+        let msg_too_many = vote_msg(
+            "some_id",
+            Choice::Multiple(vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ]),
+        );
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg_too_many).unwrap_err();
+        assert_eq!(err, ContractError::TooManyChoices { max_choices: 2 });
+        let msg_none = vote_msg("some_id", Choice::Multiple(vec![]));
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg_none).unwrap_err();
+        assert_eq!(err, ContractError::NoChoices {});
+        let msg_repeated = vote_msg(
+            "some_id",
+            Choice::Multiple(vec!["Juno".to_string(), "Juno".to_string()]),
+        );
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg_repeated).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DuplicateOption {
+                option: "Juno".to_string()
+            }
+        );
+
+        // Revoting revokes both previous choices
+        let msg_revote = vote_msg("some_id", Choice::Single("Cosmos Hub".to_string()));
+        let _res = execute(deps.as_mut(), env.clone(), info, msg_revote).unwrap();
+
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0], ("Cosmos Hub".to_string(), Uint128::new(1)));
+        assert_eq!(poll.options[1], ("Juno".to_string(), Uint128::new(0)));
+        assert_eq!(poll.options[2], ("Osmosis".to_string(), Uint128::new(0)));
+    }
+
+    #[test]
+    fn test_execute_vote_quadratic() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Without deposits a quadratic poll needs a budget
+        let options = vec![
+            "Roads".to_string(),
+            "Parks".to_string(),
+            "Schools".to_string(),
+        ];
+        let msg = NewPoll {
+            poll_id: Some("budget".to_string()),
+            question: "Where should the budget go?".to_string(),
+            options: options.clone(),
+            voting_mode: Some(VotingMode::Quadratic { budget: None }),
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidBudget {});
+
+        // Every voter gets 10 credits
+        let msg = NewPoll {
+            poll_id: Some("budget".to_string()),
+            question: "Where should the budget go?".to_string(),
+            options,
+            voting_mode: Some(VotingMode::Quadratic {
+                budget: Some(Uint128::new(10)),
+            }),
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // 3 votes on Roads and 2 on Parks cost 9 + 4 credits
+        let msg: ExecuteMsg = from_binary(
+            &br#"{"vote":{"poll_id":"budget","vote":[["Roads",3],["Parks",2]]}}"#.into(),
+        )
+        .unwrap();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NotEnoughCredits {
+                credits: Uint128::new(10)
+            }
+        );
+
+        // 3 votes on Roads and 1 on Parks cost exactly 10 credits
+        let msg = vote_msg(
+            "budget",
+            Choice::Allocation(vec![("Roads".to_string(), 3), ("Parks".to_string(), 1)]),
+        );
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = QueryMsg::Vote {
+            poll_id: "budget".to_string(),
+            address: ADDR1.to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: VoteResponse = from_binary(&bin).unwrap();
+        assert_eq!(
+            res.vote.unwrap().allocation,
+            vec![
+                ("Roads".to_string(), Uint128::new(3)),
+                ("Parks".to_string(), Uint128::new(1))
+            ]
+        );
+
+        // Revoting refunds the old allocation, so all credits can be spent again
+        let msg = vote_msg(
+            "budget",
+            Choice::Allocation(vec![("Schools".to_string(), 3)]),
+        );
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // Another voter spends their credits one vote at a time
+        let msg = vote_msg(
+            "budget",
+            Choice::Multiple(vec!["Roads".to_string(), "Schools".to_string()]),
+        );
+        let _res = execute(deps.as_mut(), env.clone(), mock_info(ADDR2, &[]), msg).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "budget".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0], ("Roads".to_string(), Uint128::new(1)));
+        assert_eq!(poll.options[1], ("Parks".to_string(), Uint128::zero()));
+        assert_eq!(poll.options[2], ("Schools".to_string(), Uint128::new(4)));
+
+        // Options need at least one vote
+        let msg = vote_msg("budget", Choice::Allocation(vec![("Roads".to_string(), 0)]));
+        let err = execute(deps.as_mut(), env, mock_info(ADDR2, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidAllocation {});
+    }
+
+    #[test]
+    fn test_group_membership() {
+        let mut app = App::default();
+        let group_code_id = app.store_code(Box::new(ContractWrapper::new(
+            cw4_group::contract::execute,
+            cw4_group::contract::instantiate,
+            cw4_group::contract::query,
+        )));
+        let code_id = app.store_code(Box::new(ContractWrapper::new(execute, instantiate, query)));
+
+        // A group in which only ADDR1 is a member, with a weight of 5
+        let msg = cw4_group::msg::InstantiateMsg {
+            admin: Some(ADDR1.to_string()),
+            members: vec![Member {
+                addr: ADDR1.to_string(),
+                weight: 5,
+            }],
+        };
+        let group_addr = app
+            .instantiate_contract(
+                group_code_id,
+                Addr::unchecked(ADDR1),
+                &msg,
+                &[],
+                "group",
+                None,
+            )
+            .unwrap();
+
+        // Restricting poll creation to members needs a group
+        let msg = InstantiateMsg {
+            members_only_create: Some(true),
+            ..Default::default()
+        };
+        let err = app
+            .instantiate_contract(code_id, Addr::unchecked(ADDR1), &msg, &[], "polls", None)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::NoGroup {}
+        );
+
+        // Instantiate the contract, gated by the group
+        let msg = InstantiateMsg {
+            group_addr: Some(group_addr.to_string()),
+            members_only_create: Some(true),
+            ..Default::default()
+        };
+        let contract_addr = app
+            .instantiate_contract(code_id, Addr::unchecked(ADDR1), &msg, &[], "polls", None)
+            .unwrap();
+        app.update_block(next_block);
+
+        // Only members can create polls
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            ..Default::default()
+        }
+        .msg();
+        let err = app
+            .execute_contract(Addr::unchecked(ADDR2), contract_addr.clone(), &msg, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::NotMember {}
+        );
+        app.execute_contract(Addr::unchecked(ADDR1), contract_addr.clone(), &msg, &[])
+            .unwrap();
+
+        // Membership changes after the poll was created do not count for it
+        let msg = cw4_group::msg::ExecuteMsg::UpdateMembers {
+            remove: vec![],
+            add: vec![
+                Member {
+                    addr: ADDR1.to_string(),
+                    weight: 10,
+                },
+                Member {
+                    addr: ADDR2.to_string(),
+                    weight: 3,
+                },
+            ],
+        };
+        app.execute_contract(Addr::unchecked(ADDR1), group_addr, &msg, &[])
+            .unwrap();
+        app.update_block(next_block);
+
+        // Only members can vote, with their member weight at the snapshot
+        let msg = vote_msg("some_id", Choice::Single("Juno".to_string()));
+        let err = app
+            .execute_contract(Addr::unchecked(ADDR2), contract_addr.clone(), &msg, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::NotMember {}
+        );
+        app.execute_contract(Addr::unchecked(ADDR1), contract_addr.clone(), &msg, &[])
+            .unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let res: PollResponse = app
+            .wrap()
+            .query_wasm_smart(contract_addr.clone(), &msg)
+            .unwrap();
+        assert_eq!(
+            res.poll.unwrap().options[0],
+            ("Juno".to_string(), Uint128::new(5))
+        );
+
+        // The group can only be removed together with the restriction on poll creation
+        let mut update_msg = ExecuteMsg::UpdateConfig {
+            max_options: None,
+            max_question_len: None,
+            max_option_len: None,
+            max_poll_id_len: None,
+            cw20_addr: None,
+            remove_cw20: None,
+            group_addr: None,
+            remove_group: Some(true),
+            members_only_create: None,
+        };
+        let err = app
+            .execute_contract(
+                Addr::unchecked(ADDR1),
+                contract_addr.clone(),
+                &update_msg,
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::NoGroup {}
+        );
+        if let ExecuteMsg::UpdateConfig {
+            members_only_create,
+            ..
+        } = &mut update_msg
+        {
+            *members_only_create = Some(false);
+        }
+        app.execute_contract(
+            Addr::unchecked(ADDR1),
+            contract_addr.clone(),
+            &update_msg,
+            &[],
+        )
+        .unwrap();
+
+        // Without the group anyone can vote again, with an equal weight
+        let msg = vote_msg("some_id", Choice::Single("Osmosis".to_string()));
+        app.execute_contract(Addr::unchecked("addr3"), contract_addr.clone(), &msg, &[])
+            .unwrap();
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let res: PollResponse = app.wrap().query_wasm_smart(contract_addr, &msg).unwrap();
+        assert_eq!(
+            res.poll.unwrap().options[1],
+            ("Osmosis".to_string(), Uint128::new(1))
+        );
+    }
+
+    #[test]
+    fn test_nft_gated_vote() {
+        // A collection in which every pair is a token id and its owner
+        fn collection(
+            owners: Vec<(&'static str, &'static str)>,
+        ) -> impl Fn(&WasmQuery) -> QuerierResult {
+            move |query| match query {
+                WasmQuery::Smart { msg, .. } => {
+                    let res = match from_binary(msg).unwrap() {
+                        Cw721QueryMsg::OwnerOf { token_id, .. } => {
+                            let owner = owners.iter().find(|token| token.0 == token_id).unwrap().1;
+                            to_binary(&OwnerOfResponse {
+                                owner: owner.to_string(),
+                                approvals: vec![],
+                            })
+                        }
+                        Cw721QueryMsg::Tokens {
+                            owner, start_after, ..
+                        } => to_binary(&TokensResponse {
+                            tokens: owners
+                                .iter()
+                                .filter(|token| token.1 == owner)
+                                .filter(|token| start_after.as_deref() < Some(token.0))
+                                .map(|token| token.0.to_string())
+                                .collect(),
+                        }),
+                        _ => unimplemented!(),
+                    };
+                    SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+                }
+                _ => unimplemented!(),
+            }
+        }
+
         let mut deps = mock_dependencies();
+        deps.querier
+            .update_wasm(collection(vec![("1", ADDR1), ("2", ADDR1), ("3", ADDR2)]));
         let env = mock_env();
-        // Send as ADDR1 to show admin is different
         let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // Create a message where ADDR2 will be an admin
-        // Have to use .to_string() method
+        // The collection address is validated
+        let msg = NewPoll {
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            vote_weight: Some(VoteWeightMsg::Cw721 {
+                address: "NFTS".to_string(),
+                per_token: true,
+            }),
+            ..Default::default()
+        }
+        .msg();
+        let _err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+
+        // One poll with a vote per token and one with a vote per holder
+        for (poll_id, per_token) in [("per_token", true), ("per_holder", false)] {
+            let msg = NewPoll {
+                poll_id: Some(poll_id.to_string()),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec!["Juno".to_string(), "Osmosis".to_string()],
+                vote_weight: Some(VoteWeightMsg::Cw721 {
+                    address: "nfts".to_string(),
+                    per_token,
+                }),
+                ..Default::default()
+            }
+            .msg();
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        // Votes need tokens owned by the voter
+        let msg = vote_msg("per_token", Choice::Single("Juno".to_string()));
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoTokens {});
+        let msg = ExecuteMsg::Vote {
+            poll_id: "per_token".to_string(),
+            vote: Choice::Single("Juno".to_string()),
+            token_ids: Some(vec!["1".to_string(), "3".to_string()]),
+            merkle_proof: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NotTokenOwner {
+                token_id: "3".to_string()
+            }
+        );
+
+        // Each token is a vote
+        let msg = ExecuteMsg::Vote {
+            poll_id: "per_token".to_string(),
+            vote: Choice::Single("Juno".to_string()),
+            token_ids: Some(vec!["1".to_string(), "2".to_string()]),
+            merkle_proof: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // After a transfer the token cannot vote again
+        deps.querier
+            .update_wasm(collection(vec![("1", ADDR2), ("2", ADDR1), ("3", ADDR2)]));
+        let msg = ExecuteMsg::Vote {
+            poll_id: "per_token".to_string(),
+            vote: Choice::Single("Osmosis".to_string()),
+            token_ids: Some(vec!["3".to_string(), "1".to_string()]),
+            merkle_proof: None,
+        };
+        let info = mock_info(ADDR2, &[]);
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TokenAlreadyVoted {
+                token_id: "1".to_string()
+            }
+        );
+        let msg = ExecuteMsg::Vote {
+            poll_id: "per_token".to_string(),
+            vote: Choice::Single("Osmosis".to_string()),
+            token_ids: Some(vec!["3".to_string()]),
+            merkle_proof: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "per_token".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0], ("Juno".to_string(), Uint128::new(2)));
+        assert_eq!(poll.options[1], ("Osmosis".to_string(), Uint128::new(1)));
+
+        // A token dropped on a revote no longer counts, so it can vote for its new owner
+        let msg = ExecuteMsg::Vote {
+            poll_id: "per_token".to_string(),
+            vote: Choice::Single("Juno".to_string()),
+            token_ids: Some(vec!["2".to_string()]),
+            merkle_proof: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info(ADDR1, &[]), msg).unwrap();
+        let msg = ExecuteMsg::Vote {
+            poll_id: "per_token".to_string(),
+            vote: Choice::Single("Osmosis".to_string()),
+            token_ids: Some(vec!["3".to_string(), "1".to_string()]),
+            merkle_proof: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "per_token".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0], ("Juno".to_string(), Uint128::new(1)));
+        assert_eq!(poll.options[1], ("Osmosis".to_string(), Uint128::new(2)));
+
+        // Failed revotes keep the tokens of the old ballot recorded
+        let used_tokens = |storage: &dyn Storage| {
+            NFT_VOTES
+                .prefix("per_token")
+                .range(storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap()
+        };
+        let tokens = used_tokens(&deps.storage);
+        assert_eq!(tokens.len(), 3);
+        let msg = ExecuteMsg::Vote {
+            poll_id: "per_token".to_string(),
+            vote: Choice::Single("DVPN".to_string()),
+            token_ids: Some(vec!["3".to_string()]),
+            merkle_proof: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidOption {
+                option: "DVPN".to_string()
+            }
+        );
+        assert_eq!(used_tokens(&deps.storage), tokens);
+        let close_msg = ExecuteMsg::ClosePoll {
+            poll_id: "per_token".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info(ADDR1, &[]), close_msg).unwrap();
+        let msg = ExecuteMsg::Vote {
+            poll_id: "per_token".to_string(),
+            vote: Choice::Single("Osmosis".to_string()),
+            token_ids: Some(vec!["3".to_string()]),
+            merkle_proof: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::PollClosed {});
+        assert_eq!(used_tokens(&deps.storage), tokens);
+
+        // Holders get a single vote, no matter how many tokens they hold
+        let msg = vote_msg("per_holder", Choice::Single("Juno".to_string()));
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("addr3", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NotTokenHolder {});
+        let _res = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap();
+
+        // The token the holder voted with cannot vote again after a transfer
+        deps.querier
+            .update_wasm(collection(vec![("1", "addr3"), ("2", ADDR1), ("3", ADDR2)]));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("addr3", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoUnusedTokens {});
+
+        let msg = QueryMsg::Poll {
+            poll_id: "per_holder".to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        assert_eq!(
+            res.poll.unwrap().options[0],
+            ("Juno".to_string(), Uint128::new(1))
+        );
+    }
+
+    // cw-multi-test 0.13 has no working staking module, so delegations are mocked
+    #[test]
+    fn test_staked_vote() {
+        fn delegation(validator: &str, amount: u128) -> FullDelegation {
+            FullDelegation {
+                delegator: Addr::unchecked(ADDR1),
+                validator: validator.to_string(),
+                amount: coin(amount, "ustake"),
+                can_redelegate: coin(amount, "ustake"),
+                accumulated_rewards: vec![],
+            }
+        }
+
+        let mut deps = mock_dependencies();
+        deps.querier.update_staking(
+            "ustake",
+            &[],
+            &[delegation("validator1", 100), delegation("validator2", 50)],
+        );
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create a stake weighted poll
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            vote_weight: Some(VoteWeightMsg::Staked),
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Without any delegations there is no vote
+        let msg = vote_msg("some_id", Choice::Single("Juno".to_string()));
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoStake {});
+
+        // The ballot is weighted by the stake across all validators
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // After undelegating most of it the recorded weight is revoked on revote
+        deps.querier
+            .update_staking("ustake", &[], &[delegation("validator1", 30)]);
+        let msg = vote_msg("some_id", Choice::Single("Osmosis".to_string()));
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0], ("Juno".to_string(), Uint128::zero()));
+        assert_eq!(poll.options[1], ("Osmosis".to_string(), Uint128::new(30)));
+    }
+
+    #[test]
+    fn test_ranked_result() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create a ranked choice poll and a single choice poll
+        for (poll_id, voting_mode) in [("ranked", Some(VotingMode::RankedChoice)), ("single", None)]
+        {
+            let msg = NewPoll {
+                poll_id: Some(poll_id.to_string()),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec![
+                    "Cosmos Hub".to_string(),
+                    "Juno".to_string(),
+                    "Osmosis".to_string(),
+                ],
+                voting_mode,
+                ..Default::default()
+            }
+            .msg();
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        // Nobody has a majority of first preferences, so Juno is eliminated
+        // and its voter's second preference decides the poll
+        let rankings = [
+            ("addr1", vec!["Cosmos Hub", "Juno"]),
+            ("addr2", vec!["Cosmos Hub"]),
+            ("addr3", vec!["Juno", "Osmosis"]),
+            ("addr4", vec!["Osmosis", "Juno"]),
+            ("addr5", vec!["Osmosis"]),
+        ];
+        for (voter, ranking) in rankings {
+            let msg = vote_msg(
+                "ranked",
+                Choice::Multiple(ranking.iter().map(|option| option.to_string()).collect()),
+            );
+            let _res = execute(deps.as_mut(), env.clone(), mock_info(voter, &[]), msg).unwrap();
+        }
+
+        // The poll itself only counts first preferences
+        let msg = QueryMsg::Poll {
+            poll_id: "ranked".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0], ("Cosmos Hub".to_string(), Uint128::new(2)));
+        assert_eq!(poll.options[1], ("Juno".to_string(), Uint128::new(1)));
+        assert_eq!(poll.options[2], ("Osmosis".to_string(), Uint128::new(2)));
+
+        let msg = QueryMsg::RankedResult {
+            poll_id: "ranked".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+        let res: RankedResultResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.rounds.len(), 2);
+        assert_eq!(res.rounds[0].eliminated, vec!["Juno"]);
+        assert_eq!(
+            res.rounds[1].counts,
+            vec![
+                ("Cosmos Hub".to_string(), Uint128::new(2)),
+                ("Osmosis".to_string(), Uint128::new(3))
+            ]
+        );
+        assert_eq!(res.winner, Some("Osmosis".to_string()));
+
+        // Changing a ranking replaces the old one, without a second preference for Juno
+        // the last two options are tied
+        let msg_revote = vote_msg("ranked", Choice::Single("Juno".to_string()));
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("addr3", &[]),
+            msg_revote,
+        )
+        .unwrap();
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: RankedResultResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.rounds.len(), 2);
+        assert_eq!(res.rounds[1].eliminated, vec!["Cosmos Hub", "Osmosis"]);
+        assert_eq!(res.winner, None);
+        // Rankings are tallied as the votes come in, the old ranking of addr3 is gone
+        assert!(!RANKED_VOTES.has(deps.as_ref().storage, ("ranked", "1,2")));
+
+        // Polls using another voting mode have no ranked result
+        let msg = QueryMsg::RankedResult {
+            poll_id: "single".to_string(),
+        };
+        let _err = query(deps.as_ref(), env, msg).unwrap_err();
+    }
+
+    #[test]
+    fn test_execute_vote_deposit() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create a poll weighted by deposits of ujuno
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            vote_weight: Some(VoteWeightMsg::Deposit {
+                denom: "ujuno".to_string(),
+            }),
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Votes without a deposit in the denom are rejected
+        let msg = vote_msg("some_id", Choice::Single("Juno".to_string()));
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+        let info = mock_info(ADDR1, &coins(100, "uosmo"));
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Payment(PaymentError::MissingDenom("ujuno".to_string()))
+        );
+
+        // The ballot is weighted by the deposit
+        let info = mock_info(ADDR1, &coins(100, "ujuno"));
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert!(res.messages.is_empty());
+
+        // Revoting moves the weight and refunds the old deposit
+        let msg = vote_msg("some_id", Choice::Single("Osmosis".to_string()));
+        let info = mock_info(ADDR1, &coins(40, "ujuno"));
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR1.to_string(),
+                amount: coins(100, "ujuno"),
+            })
+        );
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0], ("Juno".to_string(), Uint128::zero()));
+        assert_eq!(poll.options[1], ("Osmosis".to_string(), Uint128::new(40)));
+
+        // The deposit stays locked while the poll is open
+        let msg = ExecuteMsg::Withdraw {
+            poll_id: "some_id".to_string(),
+        };
+        let info = mock_info(ADDR1, &[]);
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::PollStillOpen {});
+
+        // Once closed the deposit can be withdrawn exactly once
+        let close_msg = ExecuteMsg::ClosePoll {
+            poll_id: "some_id".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), close_msg).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR1.to_string(),
+                amount: coins(40, "ujuno"),
+            })
+        );
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::NothingToWithdraw {});
+
+        // Someone who never voted has nothing to withdraw
+        let err = execute(deps.as_mut(), env, mock_info(ADDR2, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NothingToWithdraw {});
+    }
+
+    #[test]
+    fn test_execute_receive() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract with a whitelisted token
         let msg = InstantiateMsg {
-            admin: Some(ADDR2.to_string()),
+            cw20_addr: Some("token".to_string()),
+            ..Default::default()
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Polls can only be weighted by the whitelisted token
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            vote_weight: Some(VoteWeightMsg::Cw20 {
+                address: "other_token".to_string(),
+            }),
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TokenNotWhitelisted {
+                address: "other_token".to_string()
+            }
+        );
+        // The token address is validated before it is compared with the whitelist
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            vote_weight: Some(VoteWeightMsg::Cw20 {
+                address: "TOKEN".to_string(),
+            }),
+            ..Default::default()
+        }
+        .msg();
+        let _err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            vote_weight: Some(VoteWeightMsg::Cw20 {
+                address: "token".to_string(),
+            }),
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Voting without sending the token is not possible
+        let msg = vote_msg("some_id", Choice::Single("Juno".to_string()));
+        let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Cw20VoteRequired {});
+
+        // Tokens from any other contract are rejected
+        let receive_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: ADDR2.to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::Vote {
+                poll_id: "some_id".to_string(),
+                vote: Choice::Single("Juno".to_string()),
+                merkle_proof: None,
+            })
+            .unwrap(),
+        });
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("other_token", &[]),
+            receive_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::TokenNotAccepted {});
+
+        // The vote is cast for the sender of the tokens and weighted by the amount
+        let token_info = mock_info("token", &[]);
+        let _res = execute(deps.as_mut(), env.clone(), token_info.clone(), receive_msg).unwrap();
+        let msg = QueryMsg::Vote {
+            poll_id: "some_id".to_string(),
+            address: ADDR2.to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: VoteResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.vote.unwrap().weight, Uint128::new(100));
+
+        // Once the token is removed from the whitelist no new polls can use it,
+        // but the open poll still accepts it
+        let update_msg = ExecuteMsg::UpdateConfig {
+            max_options: None,
+            max_question_len: None,
+            max_option_len: None,
+            max_poll_id_len: None,
+            cw20_addr: None,
+            remove_cw20: Some(true),
+            group_addr: None,
+            remove_group: None,
+            members_only_create: None,
+        };
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR1, &[]),
+            update_msg,
+        )
+        .unwrap();
+        let msg = NewPoll {
+            poll_id: Some("some_id_2".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            vote_weight: Some(VoteWeightMsg::Cw20 {
+                address: "token".to_string(),
+            }),
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env.clone(), mock_info(ADDR1, &[]), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TokenNotWhitelisted {
+                address: "token".to_string()
+            }
+        );
+
+        // Revoting returns the earlier tokens
+        let receive_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: ADDR2.to_string(),
+            amount: Uint128::new(30),
+            msg: to_binary(&ReceiveMsg::Vote {
+                poll_id: "some_id".to_string(),
+                vote: Choice::Single("Osmosis".to_string()),
+                merkle_proof: None,
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), env.clone(), token_info, receive_msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: ADDR2.to_string(),
+                    amount: Uint128::new(100),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // The tokens are locked until the poll ends
+        let msg = ExecuteMsg::Withdraw {
+            poll_id: "some_id".to_string(),
+        };
+        let info = mock_info(ADDR2, &[]);
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::PollStillOpen {});
+        let close_msg = ExecuteMsg::ClosePoll {
+            poll_id: "some_id".to_string(),
         };
-        // Unwrap to assert success
-        let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
-        // Assert admin is ADDR2 instead
+        let _res = execute(deps.as_mut(), env.clone(), mock_info(ADDR1, &[]), close_msg).unwrap();
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(
-            res.attributes,
-            vec![attr("action", "instantiate"), attr("admin", ADDR2),]
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: ADDR2.to_string(),
+                    amount: Uint128::new(30),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
         );
     }
 
     // 11 Execute Tests
     // + #[test]
-    // + fn test_execute_create_poll_valid() {
+    // + fn test_execute_vote_invalid() {
     // +     let mut deps = mock_dependencies();
     // +     let env = mock_env();
     // +     let info = mock_info(ADDR1, &vec![]);
@@ -501,7 +3222,15 @@ mod tests {
     // +     let msg = InstantiateMsg { admin: None };
     // +     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
     // +
-    // +     // New execute msg
+    // +     // Create the vote, some_id poll is not created yet.
+    // +     let msg = ExecuteMsg::Vote {
+    // +         poll_id: "some_id".to_string(),
+    // +         vote: "Juno".to_string(),
+    // +     };
+    // +     // Unwrap to assert error
+    // +     let _err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+    // +
+    // +     // Create the poll
     // +     let msg = ExecuteMsg::CreatePoll {
     // +         poll_id: "some_id".to_string(),
     // +         question: "What's your favourite Cosmos coin?".to_string(),
@@ -511,240 +3240,541 @@ mod tests {
     // +             "Osmosis".to_string(),
     // +         ],
     // +     };
+    // +     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
     // +
-    // +     // Unwrap to assert success
-    // +     let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+    // +     // Vote on a now existing poll but the option "DVPN" does not exist
+    // +     let msg = ExecuteMsg::Vote {
+    // +         poll_id: "some_id".to_string(),
+    // +         vote: "DVPN".to_string(),
+    // +     };
+    // +     let _err = execute(deps.as_mut(), env, info, msg).unwrap_err();
     // + }
     #[test]
-    fn test_execute_create_poll_valid() {
+    fn test_execute_vote_invalid() {
         let mut deps = mock_dependencies();
         let env = mock_env();
         let info = mock_info(ADDR1, &[]);
         // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
+        let msg = InstantiateMsg::default();
         let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // New execute msg
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id".to_string(),
+        // Create the vote, some_id poll is not created yet.
+        let msg = vote_msg("some_id", Choice::Single("Juno".to_string()));
+        // Unwrap to assert error
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::PollNotFound {
+                poll_id: "some_id".to_string()
+            }
+        );
+
+        // Create the poll
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
             question: "What's your favourite Cosmos coin?".to_string(),
             options: vec![
                 "Cosmos Hub".to_string(),
                 "Juno".to_string(),
                 "Osmosis".to_string(),
             ],
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Vote on a now existing poll but the option "DVPN" does not exist
+        let msg = vote_msg("some_id", Choice::Single("DVPN".to_string()));
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidOption {
+                option: "DVPN".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_execute_create_poll_assigned_id() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create two polls without an id, the contract assigns 1 and 2
+        let msg = NewPoll {
+            question: "What's your colour?".to_string(),
+            options: vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
+            ..Default::default()
+        }
+        .msg();
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "create_poll"), attr("poll_id", "1")]
+        );
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let data: CreatePollResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(data.poll_id, "2");
+
+        // Numeric ids cannot be chosen by the client
+        let msg = NewPoll {
+            poll_id: Some("3".to_string()),
+            question: "What's your colour?".to_string(),
+            options: vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ReservedPollId {
+                poll_id: "3".to_string()
+            }
+        );
+
+        // The assigned id can be voted on and queried
+        let msg = vote_msg("2", Choice::Single("Red".to_string()));
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let msg = QueryMsg::Poll {
+            poll_id: "2".to_string(),
         };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        assert_eq!(
+            res.poll.unwrap().options[0],
+            ("Red".to_string(), Uint128::new(1))
+        );
+    }
 
-        // Unwrap to assert success
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+    #[test]
+    fn test_poll_allowlist() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // One poll only ADDR1 can vote on and one anyone can vote on
+        for (poll_id, voters) in [("team", Some(vec![ADDR1.to_string()])), ("public", None)] {
+            let msg = NewPoll {
+                poll_id: Some(poll_id.to_string()),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec!["Juno".to_string(), "Osmosis".to_string()],
+                voters,
+                ..Default::default()
+            }
+            .msg();
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        let vote_msg = vote_msg("team", Choice::Single("Juno".to_string()));
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), vote_msg.clone()).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            vote_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::VoterNotAllowed {});
+
+        // Only the creator can change the allowlist
+        let add_msg = ExecuteMsg::AddVoters {
+            poll_id: "team".to_string(),
+            voters: vec![ADDR2.to_string()],
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            add_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), add_msg).unwrap();
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            vote_msg.clone(),
+        )
+        .unwrap();
+
+        // Removed voters can no longer change their vote
+        let remove_msg = ExecuteMsg::RemoveVoters {
+            poll_id: "team".to_string(),
+            voters: vec![ADDR2.to_string()],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), remove_msg).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info(ADDR2, &[]), vote_msg).unwrap_err();
+        assert_eq!(err, ContractError::VoterNotAllowed {});
+
+        // Polls without an allowlist do not get one afterwards
+        let add_msg = ExecuteMsg::AddVoters {
+            poll_id: "public".to_string(),
+            voters: vec![ADDR2.to_string()],
+        };
+        let err = execute(deps.as_mut(), env, info, add_msg).unwrap_err();
+        assert_eq!(err, ContractError::NoAllowlist {});
     }
 
-    // 11 Execute Tests
-    // + #[test]
-    // + fn test_execute_create_poll_invalid() {
-    // +     let mut deps = mock_dependencies();
-    // +     let env = mock_env();
-    // +     let info = mock_info(ADDR1, &vec![]);
-    // +     // Instantiate the contract
-    // +     let msg = InstantiateMsg { admin: None };
-    // +     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    // +
-    // +     let msg = ExecuteMsg::CreatePoll {
-    // +         poll_id: "some_id".to_string(),
-    // +         question: "What's your favourite number?".to_string(),
-    // +         options: vec![
-    // +             "1".to_string(),
-    // +             "2".to_string(),
-    // +             "3".to_string(),
-    // +             "4".to_string(),
-    // +             "5".to_string(),
-    // +             "6".to_string(),
-    // +             "7".to_string(),
-    // +             "8".to_string(),
-    // +             "9".to_string(),
-    // +             "10".to_string(),
-    // +             "11".to_string(),
-    // +         ],
-    // +     };
-    // +
-    // +     // Unwrap error to assert failure
-    // +     let _err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-    // + }
     #[test]
-    fn test_execute_create_poll_invalid() {
+    fn test_merkle_vote() {
         let mut deps = mock_dependencies();
         let env = mock_env();
         let info = mock_info(ADDR1, &[]);
         // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
+        let msg = InstantiateMsg::default();
         let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id".to_string(),
-            question: "What's your favourite number?".to_string(),
-            options: vec![
-                "1".to_string(),
-                "2".to_string(),
-                "3".to_string(),
-                "4".to_string(),
-                "5".to_string(),
-                "6".to_string(),
-                "7".to_string(),
-                "8".to_string(),
-                "9".to_string(),
-                "10".to_string(),
-                "11".to_string(),
-            ],
+        // A tree of ADDR1 with a weight of 3 and ADDR2 without a weight
+        let leaf1 = merkle_leaf(ADDR1, Some(Uint128::new(3)));
+        let leaf2 = merkle_leaf(ADDR2, None);
+        let root = merkle_parent(leaf1, leaf2);
+
+        // The root has to be 32 bytes
+        let create_msg = |merkle_root: String| {
+            NewPoll {
+                poll_id: Some("some_id_1".to_string()),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec!["Juno".to_string(), "Osmosis".to_string()],
+                merkle_root: Some(merkle_root),
+                ..Default::default()
+            }
+            .msg()
         };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            create_msg("abcd".to_string()),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidMerkleRoot {});
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            create_msg(hex::encode_upper(root)),
+        )
+        .unwrap();
 
-        // Unwrap error to assert failure
-        let _err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        // Votes need a proof
+        let vote = |weight: Option<u128>, proof: Option<[u8; 32]>| ExecuteMsg::Vote {
+            poll_id: "some_id_1".to_string(),
+            vote: Choice::Single("Juno".to_string()),
+            token_ids: None,
+            merkle_proof: proof.map(|proof| MerkleProof {
+                weight: weight.map(Uint128::new),
+                proof: vec![hex::encode(proof)],
+            }),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), vote(None, None)).unwrap_err();
+        assert_eq!(err, ContractError::MissingProof {});
+
+        // The weight is part of the leaf, so it cannot be changed
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            vote(Some(30), Some(leaf2)),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        let _res = execute(deps.as_mut(), env.clone(), info, vote(Some(3), Some(leaf2))).unwrap();
+
+        // ADDR2 votes with a weight of 1, the proof of ADDR2 does not work for addr3
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            vote(None, Some(leaf1)),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("addr3", &[]),
+            vote(None, Some(leaf1)),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id_1".to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.poll.unwrap().options[0].1, Uint128::new(4));
     }
 
-    // 11 Execute Tests
-    // + #[test]
-    // + fn test_execute_vote_valid() {
-    // +     let mut deps = mock_dependencies();
-    // +     let env = mock_env();
-    // +     let info = mock_info(ADDR1, &vec![]);
-    // +     // Instantiate the contract
-    // +     let msg = InstantiateMsg { admin: None };
-    // +     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    // +
-    // +     // Create the poll
-    // +     let msg = ExecuteMsg::CreatePoll {
-    // +         poll_id: "some_id".to_string(),
-    // +         question: "What's your favourite Cosmos coin?".to_string(),
-    // +         options: vec![
-    // +             "Cosmos Hub".to_string(),
-    // +             "Juno".to_string(),
-    // +             "Osmosis".to_string(),
-    // +         ],
-    // +     };
-    // +     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    // +
-    // +     // Create the vote, first time voting
-    // +     let msg = ExecuteMsg::Vote {
-    // +         poll_id: "some_id".to_string(),
-    // +         vote: "Juno".to_string(),
-    // +     };
-    // +     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    // +
-    // +     // Change the vote
-    // +     let msg = ExecuteMsg::Vote {
-    // +         poll_id: "some_id".to_string(),
-    // +         vote: "Osmosis".to_string(),
-    // +     };
-    // +     let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-    // + }
     #[test]
-    fn test_execute_vote_valid() {
+    fn test_merkle_csv() {
         let mut deps = mock_dependencies();
         let env = mock_env();
         let info = mock_info(ADDR1, &[]);
         // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
+        let msg = InstantiateMsg::default();
         let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // Create the poll
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id".to_string(),
+        // The tree the merkle example builds from its sample CSV
+        let voters = merkle_voters(include_str!("../examples/merkle_voters.csv")).unwrap();
+        let (merkle_root, proofs) = merkle_tree(&voters).unwrap();
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
             question: "What's your favourite Cosmos coin?".to_string(),
-            options: vec![
-                "Cosmos Hub".to_string(),
-                "Juno".to_string(),
-                "Osmosis".to_string(),
-            ],
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            merkle_root: Some(merkle_root),
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // The address written in uppercase is lowercased like the sender, and the last
+        // voter, whose hash is moved up unpaired, has a shorter proof
+        assert_eq!(voters[1].0, "juno1xfm6gz8c2xnyqq3dlg6xg6pmkm0kmg3zhy0n2k");
+        for (index, option) in [(1, "Juno"), (4, "Osmosis")] {
+            let msg = ExecuteMsg::Vote {
+                poll_id: "some_id".to_string(),
+                vote: Choice::Single(option.to_string()),
+                token_ids: None,
+                merkle_proof: Some(proofs[index].clone()),
+            };
+            let _res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(&voters[index].0, &[]),
+                msg,
+            )
+            .unwrap();
+        }
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.options[0].1, Uint128::new(5));
+        assert_eq!(poll.options[1].1, Uint128::new(10));
+    }
+
+    #[test]
+    fn test_execute_close_poll() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract with ADDR2 as admin
+        let msg = InstantiateMsg {
+            admin: Some(ADDR2.to_string()),
+            ..Default::default()
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create two polls as ADDR1
+        for poll_id in ["some_id_1", "some_id_2"] {
+            let msg = NewPoll {
+                poll_id: Some(poll_id.to_string()),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec![
+                    "Cosmos Hub".to_string(),
+                    "Juno".to_string(),
+                    "Osmosis".to_string(),
+                ],
+                ..Default::default()
+            }
+            .msg();
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        // A poll which does not exist cannot be closed
+        let msg = ExecuteMsg::ClosePoll {
+            poll_id: "some_id_3".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::PollNotFound {
+                poll_id: "some_id_3".to_string()
+            }
+        );
+
+        // Someone who is neither the creator nor the admin cannot close the poll
+        let msg = ExecuteMsg::ClosePoll {
+            poll_id: "some_id_1".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("addr3", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // The creator can close their poll
+        let msg = ExecuteMsg::ClosePoll {
+            poll_id: "some_id_1".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // The admin can close any poll
+        let msg = ExecuteMsg::ClosePoll {
+            poll_id: "some_id_2".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info(ADDR2, &[]), msg).unwrap();
+
+        // A closed poll cannot be closed again
+        let msg = ExecuteMsg::ClosePoll {
+            poll_id: "some_id_2".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::PollClosed {});
+
+        // Votes are rejected once the poll is closed
+        let msg = vote_msg("some_id_1", Choice::Single("Juno".to_string()));
+        let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::PollClosed {});
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id_1".to_string(),
         };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.poll.unwrap().status, PollStatus::Closed);
+    }
+
+    #[test]
+    fn test_execute_finalize_poll() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // One poll ending in 10 blocks and one without an end
+        for (poll_id, end) in [
+            ("ending", Some(Expiration::AtHeight(env.block.height + 10))),
+            ("closing", None),
+        ] {
+            let msg = NewPoll {
+                poll_id: Some(poll_id.to_string()),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec!["Juno".to_string(), "Osmosis".to_string()],
+                end,
+                ..Default::default()
+            }
+            .msg();
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+        let msg = vote_msg("ending", Choice::Single("Juno".to_string()));
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // Create the vote, first time voting
-        let msg = ExecuteMsg::Vote {
-            poll_id: "some_id".to_string(),
-            vote: "Juno".to_string(),
+        // Polls which still accept votes cannot be finalized
+        let finalize_msg = |poll_id: &str| ExecuteMsg::FinalizePoll {
+            poll_id: poll_id.to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            finalize_msg("ending"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::PollNotEnded {});
+
+        // Anyone can finalize a poll which has ended, but only once
+        env.block.height += 10;
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADDR2, &[]),
+            finalize_msg("ending"),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            finalize_msg("ending"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::PollFinalized {});
+
+        // Polls without an end have to be closed first
+        let msg = ExecuteMsg::ClosePoll {
+            poll_id: "closing".to_string(),
         };
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let _res = execute(deps.as_mut(), env.clone(), info, finalize_msg("closing")).unwrap();
 
-        // Change the vote
-        let msg = ExecuteMsg::Vote {
-            poll_id: "some_id".to_string(),
-            vote: "Osmosis".to_string(),
+        let msg = QueryMsg::Poll {
+            poll_id: "ending".to_string(),
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.status, PollStatus::Finalized);
+        assert_eq!(poll.options[0].1, Uint128::new(1));
     }
 
-    // 11 Execute Tests
-    // + #[test]
-    // + fn test_execute_vote_invalid() {
-    // +     let mut deps = mock_dependencies();
-    // +     let env = mock_env();
-    // +     let info = mock_info(ADDR1, &vec![]);
-    // +     // Instantiate the contract
-    // +     let msg = InstantiateMsg { admin: None };
-    // +     let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    // +
-    // +     // Create the vote, some_id poll is not created yet.
-    // +     let msg = ExecuteMsg::Vote {
-    // +         poll_id: "some_id".to_string(),
-    // +         vote: "Juno".to_string(),
-    // +     };
-    // +     // Unwrap to assert error
-    // +     let _err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-    // +
-    // +     // Create the poll
-    // +     let msg = ExecuteMsg::CreatePoll {
-    // +         poll_id: "some_id".to_string(),
-    // +         question: "What's your favourite Cosmos coin?".to_string(),
-    // +         options: vec![
-    // +             "Cosmos Hub".to_string(),
-    // +             "Juno".to_string(),
-    // +             "Osmosis".to_string(),
-    // +         ],
-    // +     };
-    // +     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    // +
-    // +     // Vote on a now existing poll but the option "DVPN" does not exist
-    // +     let msg = ExecuteMsg::Vote {
-    // +         poll_id: "some_id".to_string(),
-    // +         vote: "DVPN".to_string(),
-    // +     };
-    // +     let _err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-    // + }
     #[test]
-    fn test_execute_vote_invalid() {
+    fn test_execute_vote_time_bounds() {
         let mut deps = mock_dependencies();
-        let env = mock_env();
+        let mut env = mock_env();
         let info = mock_info(ADDR1, &[]);
         // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
+        let msg = InstantiateMsg::default();
         let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // Create the vote, some_id poll is not created yet.
-        let msg = ExecuteMsg::Vote {
-            poll_id: "some_id".to_string(),
-            vote: "Juno".to_string(),
-        };
-        // Unwrap to assert error
-        let _err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        // A poll cannot end before it has been created
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            end: Some(Expiration::AtHeight(env.block.height)),
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidExpiration {});
 
-        // Create the poll
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id".to_string(),
+        // Nor can it never start
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
             question: "What's your favourite Cosmos coin?".to_string(),
-            options: vec![
-                "Cosmos Hub".to_string(),
-                "Juno".to_string(),
-                "Osmosis".to_string(),
-            ],
-        };
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            start: Some(Expiration::Never {}),
+            ..Default::default()
+        }
+        .msg();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidExpiration {});
+
+        // Create a poll running from 10 blocks to 20 blocks from now
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            start: Some(Expiration::AtHeight(env.block.height + 10)),
+            end: Some(Expiration::AtHeight(env.block.height + 20)),
+            ..Default::default()
+        }
+        .msg();
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // Vote on a now existing poll but the option "DVPN" does not exist
-        let msg = ExecuteMsg::Vote {
-            poll_id: "some_id".to_string(),
-            vote: "DVPN".to_string(),
-        };
-        let _err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        // The poll has not started yet
+        let msg = vote_msg("some_id", Choice::Single("Juno".to_string()));
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::PollNotStarted {});
+
+        // Once started votes are accepted
+        env.block.height += 10;
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+
+        // After the end no more votes are accepted
+        env.block.height += 10;
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(err, ContractError::PollExpired {});
     }
 
     // 14 Query Tests
@@ -789,34 +3819,122 @@ mod tests {
         let env = mock_env();
         let info = mock_info(ADDR1, &[]);
         // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
+        let msg = InstantiateMsg::default();
         let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
         // Create a poll
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id_1".to_string(),
+        let msg = NewPoll {
+            poll_id: Some("some_id_1".to_string()),
             question: "What's your favourite Cosmos coin?".to_string(),
             options: vec![
                 "Cosmos Hub".to_string(),
                 "Juno".to_string(),
                 "Osmosis".to_string(),
             ],
-        };
+            ..Default::default()
+        }
+        .msg();
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
         // Create a second poll
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id_2".to_string(),
+        let msg = NewPoll {
+            poll_id: Some("some_id_2".to_string()),
             question: "What's your colour?".to_string(),
             options: vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
-        };
+            ..Default::default()
+        }
+        .msg();
         let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
         // Query
-        let msg = QueryMsg::AllPolls {};
-        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let msg = QueryMsg::AllPolls {
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
         let res: AllPollsResponse = from_binary(&bin).unwrap();
         assert_eq!(res.polls.len(), 2);
+        assert_eq!(res.polls[0].0, "some_id_1");
+
+        // Query the first page of one poll
+        let msg = QueryMsg::AllPolls {
+            start_after: None,
+            limit: Some(1),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: AllPollsResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].0, "some_id_1");
+
+        // Query the next page
+        let msg = QueryMsg::AllPolls {
+            start_after: Some("some_id_1".to_string()),
+            limit: Some(1),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: AllPollsResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].0, "some_id_2");
+        assert_eq!(res.polls[0].1.question, "What's your colour?");
+    }
+
+    #[test]
+    fn test_query_polls_by_creator() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // ADDR1 creates two polls, ADDR2 creates one
+        for (creator, poll_id) in [
+            (ADDR1, "some_id_1"),
+            (ADDR2, "some_id_2"),
+            (ADDR1, "some_id_3"),
+        ] {
+            let msg = NewPoll {
+                poll_id: Some(poll_id.to_string()),
+                question: "What's your colour?".to_string(),
+                options: vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
+                ..Default::default()
+            }
+            .msg();
+            let _res = execute(deps.as_mut(), env.clone(), mock_info(creator, &[]), msg).unwrap();
+        }
+
+        // Query the polls of ADDR1
+        let msg = QueryMsg::PollsByCreator {
+            creator: ADDR1.to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: AllPollsResponse = from_binary(&bin).unwrap();
+        let poll_ids: Vec<_> = res.polls.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(poll_ids, vec!["some_id_1", "some_id_3"]);
+
+        // Query the next page of the polls of ADDR1
+        let msg = QueryMsg::PollsByCreator {
+            creator: ADDR1.to_string(),
+            start_after: Some("some_id_1".to_string()),
+            limit: Some(1),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: AllPollsResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].0, "some_id_3");
+
+        // Query the polls of ADDR2
+        let msg = QueryMsg::PollsByCreator {
+            creator: ADDR2.to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: AllPollsResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].0, "some_id_2");
     }
 
     // 14 Query Tests
@@ -865,19 +3983,21 @@ mod tests {
         let env = mock_env();
         let info = mock_info(ADDR1, &[]);
         // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
+        let msg = InstantiateMsg::default();
         let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
         // Create a poll
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id_1".to_string(),
+        let msg = NewPoll {
+            poll_id: Some("some_id_1".to_string()),
             question: "What's your favourite Cosmos coin?".to_string(),
             options: vec![
                 "Cosmos Hub".to_string(),
                 "Juno".to_string(),
                 "Osmosis".to_string(),
             ],
-        };
+            ..Default::default()
+        }
+        .msg();
         let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
         // Query for the poll that exists
@@ -954,26 +4074,25 @@ mod tests {
         let env = mock_env();
         let info = mock_info(ADDR1, &[]);
         // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
+        let msg = InstantiateMsg::default();
         let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
         // Create a poll
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id_1".to_string(),
+        let msg = NewPoll {
+            poll_id: Some("some_id_1".to_string()),
             question: "What's your favourite Cosmos coin?".to_string(),
             options: vec![
                 "Cosmos Hub".to_string(),
                 "Juno".to_string(),
                 "Osmosis".to_string(),
             ],
-        };
+            ..Default::default()
+        }
+        .msg();
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
         // Create a vote
-        let msg = ExecuteMsg::Vote {
-            poll_id: "some_id_1".to_string(),
-            vote: "Juno".to_string(),
-        };
+        let msg = vote_msg("some_id_1", Choice::Single("Juno".to_string()));
         let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
         // Query for a vote that exists
@@ -996,4 +4115,316 @@ mod tests {
         // Expect the vote to not exist
         assert!(res.vote.is_none());
     }
+
+    #[test]
+    fn test_query_vote_lists() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create two polls
+        for poll_id in ["some_id_1", "some_id_2"] {
+            let msg = NewPoll {
+                poll_id: Some(poll_id.to_string()),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec![
+                    "Cosmos Hub".to_string(),
+                    "Juno".to_string(),
+                    "Osmosis".to_string(),
+                ],
+                ..Default::default()
+            }
+            .msg();
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        // ADDR1 and ADDR2 vote on the first poll, ADDR1 also votes on the second
+        for (voter, poll_id, vote) in [
+            (ADDR1, "some_id_1", "Juno"),
+            (ADDR2, "some_id_1", "Osmosis"),
+            (ADDR1, "some_id_2", "Cosmos Hub"),
+        ] {
+            let msg = vote_msg(poll_id, Choice::Single(vote.to_string()));
+            let _res = execute(deps.as_mut(), env.clone(), mock_info(voter, &[]), msg).unwrap();
+        }
+
+        // List the votes of the first poll
+        let msg = QueryMsg::ListVotes {
+            poll_id: "some_id_1".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ListVotesResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.votes.len(), 2);
+        assert_eq!(res.votes[0].0, ADDR1);
+        assert_eq!(res.votes[0].1.options, vec!["Juno"]);
+        assert_eq!(res.votes[1].0, ADDR2);
+        assert_eq!(res.votes[1].1.options, vec!["Osmosis"]);
+
+        // List the next page after ADDR1
+        let msg = QueryMsg::ListVotes {
+            poll_id: "some_id_1".to_string(),
+            start_after: Some(ADDR1.to_string()),
+            limit: Some(1),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ListVotesResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.votes.len(), 1);
+        assert_eq!(res.votes[0].0, ADDR2);
+
+        // List every vote of ADDR1
+        let msg = QueryMsg::VotesByVoter {
+            address: ADDR1.to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: VotesByVoterResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.votes.len(), 2);
+        assert_eq!(res.votes[0].0, "some_id_1");
+        assert_eq!(res.votes[0].1.options, vec!["Juno"]);
+        assert_eq!(res.votes[1].0, "some_id_2");
+        assert_eq!(res.votes[1].1.options, vec!["Cosmos Hub"]);
+
+        // List the votes of ADDR1 after the first poll
+        let msg = QueryMsg::VotesByVoter {
+            address: ADDR1.to_string(),
+            start_after: Some("some_id_1".to_string()),
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: VotesByVoterResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.votes.len(), 1);
+        assert_eq!(res.votes[0].0, "some_id_2");
+    }
+
+    #[test]
+    fn test_migrate() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Pretend this is a 0.1.0 deployment holding polls and a ballot in the old layout
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.1.0").unwrap();
+        let legacy_config = LegacyConfig {
+            admin: Addr::unchecked(ADDR1),
+        };
+        LEGACY_CONFIG
+            .save(deps.as_mut().storage, &legacy_config)
+            .unwrap();
+        let legacy_poll = LegacyPoll {
+            creator: Addr::unchecked(ADDR1),
+            question: "What's your colour?".to_string(),
+            options: vec![("Red".to_string(), 1), ("Green".to_string(), 0)],
+        };
+        LEGACY_POLLS
+            .save(deps.as_mut().storage, "some_id", &legacy_poll)
+            .unwrap();
+        LEGACY_POLLS
+            .save(deps.as_mut().storage, "7", &legacy_poll)
+            .unwrap();
+        let legacy_ballot = LegacyBallot {
+            option: "Red".to_string(),
+        };
+        LEGACY_BALLOTS
+            .save(
+                deps.as_mut().storage,
+                (Addr::unchecked(ADDR2), "some_id"),
+                &legacy_ballot,
+            )
+            .unwrap();
+
+        let _res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+        let version = get_contract_version(deps.as_ref().storage).unwrap();
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+
+        // The config has the default limits
+        let bin = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let res: ConfigResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.admin, Some(Addr::unchecked(ADDR1)));
+        assert_eq!(res.max_options, 10);
+
+        // The poll is readable in the new layout and reachable through the creator index
+        let msg = QueryMsg::PollsByCreator {
+            creator: ADDR1.to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: AllPollsResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.polls.len(), 2);
+        assert_eq!(res.polls[0].1.status, PollStatus::Open);
+        assert_eq!(res.polls[0].1.snapshot_height, env.block.height);
+
+        // The ballot is reachable through the poll index
+        let msg = QueryMsg::ListVotes {
+            poll_id: "some_id".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ListVotesResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.votes.len(), 1);
+        assert_eq!(res.votes[0].1.options, vec!["Red"]);
+
+        // Assigned ids start after the largest numeric id of the migrated polls
+        let msg = NewPoll {
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            ..Default::default()
+        }
+        .msg();
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let data: CreatePollResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(data.poll_id, "8");
+
+        // Downgrades are refused
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "9.9.9").unwrap();
+        let err = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CannotMigrateVersion {
+                previous_version: "9.9.9".to_string()
+            }
+        );
+
+        // As is migrating from another contract
+        set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.1.0").unwrap();
+        let err = migrate(deps.as_mut(), env, MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CannotMigrate {
+                previous_contract: "crates.io:cw20-base".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_migrate_numeric_poll_ids() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // A 0.1.0 deployment where a client chose the numeric id "1"
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.1.0").unwrap();
+        let legacy_config = LegacyConfig {
+            admin: Addr::unchecked(ADDR1),
+        };
+        LEGACY_CONFIG
+            .save(deps.as_mut().storage, &legacy_config)
+            .unwrap();
+        let legacy_poll = LegacyPoll {
+            creator: Addr::unchecked(ADDR2),
+            question: "What's your colour?".to_string(),
+            options: vec![("Red".to_string(), 1), ("Green".to_string(), 0)],
+        };
+        LEGACY_POLLS
+            .save(deps.as_mut().storage, "1", &legacy_poll)
+            .unwrap();
+        let _res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+        // The contract does not assign the id which is already taken
+        let msg = NewPoll {
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            ..Default::default()
+        }
+        .msg();
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let data: CreatePollResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_ne!(data.poll_id, "1");
+
+        let msg = QueryMsg::Poll {
+            poll_id: "1".to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.question, "What's your colour?");
+        assert_eq!(poll.options[0].1, Uint128::new(1));
+    }
+
+    #[test]
+    fn test_execute_vote_invalid_keeps_state() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(ADDR1, &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg::default();
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create the poll and vote
+        let msg = NewPoll {
+            poll_id: Some("some_id".to_string()),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Juno".to_string(), "Osmosis".to_string()],
+            ..Default::default()
+        }
+        .msg();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = vote_msg("some_id", Choice::Single("Juno".to_string()));
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Changing the vote to an option which does not exist leaves the ballot and tally untouched
+        let msg = vote_msg("some_id", Choice::Single("DVPN".to_string()));
+        let _err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        let msg = QueryMsg::Vote {
+            poll_id: "some_id".to_string(),
+            address: ADDR1.to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: VoteResponse = from_binary(&bin).unwrap();
+        assert_eq!(res.vote.unwrap().options, vec!["Juno"]);
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_binary(&bin).unwrap();
+        assert_eq!(
+            res.poll.unwrap().options[0],
+            ("Juno".to_string(), Uint128::new(1))
+        );
+
+        // A stored ballot for an option the poll does not have is an error rather than a panic
+        let ballot = Ballot {
+            poll_id: "some_id".to_string(),
+            options: vec!["Cosmos Hub".to_string()],
+            allocation: vec![("Cosmos Hub".to_string(), Uint128::new(1))],
+            weight: Uint128::new(1),
+            deposit: Uint128::zero(),
+            token_ids: vec![],
+        };
+        ballots()
+            .save(
+                deps.as_mut().storage,
+                (Addr::unchecked(ADDR2), "some_id"),
+                &ballot,
+            )
+            .unwrap();
+        let msg = vote_msg("some_id", Choice::Single("Osmosis".to_string()));
+        let err = execute(deps.as_mut(), env.clone(), mock_info(ADDR2, &[]), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidOption {
+                option: "Cosmos Hub".to_string()
+            }
+        );
+
+        // Querying the vote of a malformed address is an error rather than a panic
+        let msg = QueryMsg::Vote {
+            poll_id: "some_id".to_string(),
+            address: "A".to_string(),
+        };
+        let _err = query(deps.as_ref(), env, msg).unwrap_err();
+    }
 }
@@ -1,14 +1,38 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{OverflowError, StdError, Uint128};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("No admin has been proposed")]
+    NoPendingAdmin {},
+
+    #[error(
+        "Limits must allow at least two options and non-empty questions, options and poll ids"
+    )]
+    InvalidConfig {},
+
+    #[error("Semver parsing error: {0}")]
+    SemVer(String),
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from newer version {previous_version}")]
+    CannotMigrateVersion { previous_version: String },
+
     // 09 Execute 1
     // - #[error("Custom Error val: {val:?}")]
     // - CustomError { val: String },
@@ -16,4 +40,142 @@ pub enum ContractError {
     // + TooManyOptions {},
     #[error("Too many poll options")]
     TooManyOptions {},
+
+    #[error("Poll must have at least two options")]
+    TooFewOptions {},
+
+    #[error("Poll id cannot be empty")]
+    EmptyPollId {},
+
+    #[error("Poll question cannot be empty")]
+    EmptyQuestion {},
+
+    #[error("Poll options cannot be empty")]
+    EmptyOption {},
+
+    #[error("Poll option {option} is given more than once")]
+    DuplicateOption { option: String },
+
+    #[error("Poll {poll_id} does not exist")]
+    PollNotFound { poll_id: String },
+
+    #[error("Poll {poll_id} already exists")]
+    DuplicatePollId { poll_id: String },
+
+    #[error("Numeric poll id {poll_id} is reserved for ids assigned by the contract")]
+    ReservedPollId { poll_id: String },
+
+    #[error("Poll has no option {option}")]
+    InvalidOption { option: String },
+
+    #[error("Poll question is too long")]
+    QuestionTooLong {},
+
+    #[error("Poll option {option} is too long")]
+    OptionTooLong { option: String },
+
+    #[error("Poll id {poll_id} is too long")]
+    PollIdTooLong { poll_id: String },
+
+    #[error("Poll is not open")]
+    PollClosed {},
+
+    #[error("Poll has not started yet")]
+    PollNotStarted {},
+
+    #[error("Poll has ended")]
+    PollExpired {},
+
+    #[error("Poll can only be finalized once it no longer accepts votes")]
+    PollNotEnded {},
+
+    #[error("Poll has already been finalized")]
+    PollFinalized {},
+
+    #[error("Poll must start at some point and end in the future after it starts")]
+    InvalidExpiration {},
+
+    #[error("Approval polls must allow between one and all of their options to be chosen")]
+    InvalidMaxChoices {},
+
+    #[error("Vote must choose at least one option")]
+    NoChoices {},
+
+    #[error("Vote may choose at most {max_choices} options")]
+    TooManyChoices { max_choices: u32 },
+
+    #[error("Options need at least one vote, and exactly one unless the poll is quadratic")]
+    InvalidAllocation {},
+
+    #[error("Vote costs more than the {credits} available credits")]
+    NotEnoughCredits { credits: Uint128 },
+
+    #[error("Quadratic polls need a non-zero budget unless credits are deposited")]
+    InvalidBudget {},
+
+    #[error("Deposit polls need the denom of the coin to deposit")]
+    EmptyDenom {},
+
+    #[error("Deposits can only be withdrawn once the poll no longer accepts votes")]
+    PollStillOpen {},
+
+    #[error("Nothing to withdraw")]
+    NothingToWithdraw {},
+
+    #[error("Votes on this poll have to be sent with its cw20 token")]
+    Cw20VoteRequired {},
+
+    #[error("Token {address} is not whitelisted")]
+    TokenNotWhitelisted { address: String },
+
+    #[error("Poll does not accept votes with this token")]
+    TokenNotAccepted {},
+
+    #[error("Only members of the group can do this")]
+    NotMember {},
+
+    #[error("Poll creation can only be restricted to members when there is a group")]
+    NoGroup {},
+
+    #[error("Only holders of the collection can vote")]
+    NotTokenHolder {},
+
+    #[error("Vote must be cast with at least one token")]
+    NoTokens {},
+
+    #[error("Token {token_id} is not owned by the voter")]
+    NotTokenOwner { token_id: String },
+
+    #[error("Token {token_id} is given more than once")]
+    DuplicateTokenId { token_id: String },
+
+    #[error("Token {token_id} has already been used to vote on this poll")]
+    TokenAlreadyVoted { token_id: String },
+
+    #[error("Every token of the voter has already been used to vote on this poll")]
+    NoUnusedTokens {},
+
+    #[error("Only addresses with delegated stake can vote")]
+    NoStake {},
+
+    #[error("Voter is not on the allowlist of this poll")]
+    VoterNotAllowed {},
+
+    #[error("Poll has no allowlist")]
+    NoAllowlist {},
+
+    #[error("Merkle root must be 32 hex encoded bytes")]
+    InvalidMerkleRoot {},
+
+    #[error("Votes on this poll need a merkle proof")]
+    MissingProof {},
+
+    #[error("Merkle proof is not valid for this voter")]
+    InvalidProof {},
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
 }
@@ -45,3 +45,85 @@
 // -         Ok(res)
 // -     }
 // - }
+
+use cosmwasm_std::{StdError, StdResult, Uint128};
+use sha2::{Digest, Sha256};
+
+use crate::msg::MerkleProof;
+
+// Merkle tree hashing, shared by the contract and the merkle example.
+// A leaf is the hash of the address, or of "address,weight" when the voter has a weight.
+// A parent is the hash of its two children in ascending order, so proofs do not need to
+// say on which side each sibling is.
+pub fn merkle_leaf(address: &str, weight: Option<Uint128>) -> [u8; 32] {
+    let leaf = match weight {
+        Some(weight) => format!("{},{}", address, weight),
+        None => address.to_string(),
+    };
+    Sha256::digest(leaf.as_bytes()).into()
+}
+
+pub fn merkle_parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let mut hashes = [a, b];
+    hashes.sort_unstable();
+    Sha256::digest(&hashes.concat()).into()
+}
+
+// Parses a CSV with a line of "address" or "address,weight" per voter. Addresses are
+// lowercased, as bech32 addresses are only canonical in lowercase and the contract
+// hashes the canonical address of the voter.
+pub fn merkle_voters(csv: &str) -> StdResult<Vec<(String, Option<Uint128>)>> {
+    csv.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(',') {
+            Some((address, weight)) => {
+                let weight = weight.trim().parse::<u128>().map_err(|_| {
+                    StdError::generic_err(format!("Invalid weight in line {}", line))
+                })?;
+                Ok((address.trim().to_lowercase(), Some(Uint128::new(weight))))
+            }
+            None => Ok((line.to_lowercase(), None)),
+        })
+        .collect()
+}
+
+// Builds the merkle tree of the voters, returning the hex encoded root and the proof of
+// every voter in the same order. Every level pairs up the hashes of the one below,
+// an odd hash out is moved up as is.
+pub fn merkle_tree(voters: &[(String, Option<Uint128>)]) -> StdResult<(String, Vec<MerkleProof>)> {
+    if voters.is_empty() {
+        return Err(StdError::generic_err(
+            "A merkle tree needs at least one voter",
+        ));
+    }
+    let mut levels = vec![voters
+        .iter()
+        .map(|(address, weight)| merkle_leaf(address, *weight))
+        .collect::<Vec<_>>()];
+    while levels[levels.len() - 1].len() > 1 {
+        let level = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => merkle_parent(*a, *b),
+                _ => pair[0],
+            })
+            .collect();
+        levels.push(level);
+    }
+
+    let proofs = voters
+        .iter()
+        .enumerate()
+        .map(|(index, (_, weight))| MerkleProof {
+            weight: *weight,
+            proof: levels
+                .iter()
+                .enumerate()
+                .filter_map(|(height, level)| level.get((index >> height) ^ 1))
+                .map(hex::encode)
+                .collect(),
+        })
+        .collect();
+    Ok((hex::encode(levels[levels.len() - 1][0]), proofs))
+}
@@ -2,7 +2,10 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 // 13 Query
 // + use crate::state:{Poll, Ballot};
-use crate::state::{Ballot, Poll};
+use crate::state::{Ballot, Poll, VotingMode};
+use cosmwasm_std::{Addr, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw_utils::Expiration;
 
 // 06 Instantiate
 // - #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -15,10 +18,21 @@ use crate::state::{Ballot, Poll};
 // + pub struct InstantiateMsg {
 // +     pub admin: Option<String>,
 // + }
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct InstantiateMsg {
     pub admin: Option<String>,
+    // Limits applied to new polls, the defaults are used when not given
+    pub max_options: Option<u32>,
+    pub max_question_len: Option<u32>,
+    pub max_option_len: Option<u32>,
+    pub max_poll_id_len: Option<u32>,
+    // The cw20 token which can be sent to vote on Cw20 weighted polls
+    pub cw20_addr: Option<String>,
+    // The cw4 group whose members can vote, anyone can vote when not given
+    pub group_addr: Option<String>,
+    // Defaults to anyone being able to create polls
+    pub members_only_create: Option<bool>,
 }
 
 // 08 ExecuteMsg
@@ -44,14 +58,114 @@ pub struct InstantiateMsg {
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     CreatePoll {
-        poll_id: String,
+        // When no id is given the contract assigns the next number in its sequence.
+        // Numeric ids are reserved for this, so client provided ids cannot collide with them.
+        poll_id: Option<String>,
         question: String,
+        // Names are stored without surrounding whitespace and have to be unique
         options: Vec<String>,
+        start: Option<Expiration>,
+        end: Option<Expiration>,
+        // Defaults to single choice voting
+        voting_mode: Option<VotingMode>,
+        // Defaults to every ballot counting equally
+        vote_weight: Option<VoteWeightMsg>,
+        // When given only these addresses can vote, the list can be changed by the creator
+        voters: Option<Vec<String>>,
+        // When given only addresses with a proof for this hex encoded root can vote
+        merkle_root: Option<String>,
     },
     Vote {
         poll_id: String,
-        vote: String,
+        vote: Choice,
+        // The NFTs to vote with, for polls with one vote per cw721 token
+        token_ids: Option<Vec<String>>,
+        // Required for polls with a merkle root
+        merkle_proof: Option<MerkleProof>,
+    },
+    ClosePoll {
+        poll_id: String,
+    },
+    // Anyone can finalize a poll once it is closed or has ended
+    FinalizePoll {
+        poll_id: String,
+    },
+    // Only the creator of a poll with an allowlist can change it
+    AddVoters {
+        poll_id: String,
+        voters: Vec<String>,
     },
+    RemoveVoters {
+        poll_id: String,
+        voters: Vec<String>,
+    },
+    // Votes on Cw20 weighted polls are sent with the tokens, wrapping a ReceiveMsg
+    Receive(Cw20ReceiveMsg),
+    // Returns the deposit of the sender's ballot once the poll no longer accepts votes
+    Withdraw {
+        poll_id: String,
+    },
+    // Admin transfer is two-step, the proposed admin has to accept before taking over
+    ProposeNewAdmin {
+        new_admin: String,
+    },
+    AcceptAdmin {},
+    RenounceAdmin {},
+    // Only the given limits are changed
+    UpdateConfig {
+        max_options: Option<u32>,
+        max_question_len: Option<u32>,
+        max_option_len: Option<u32>,
+        max_poll_id_len: Option<u32>,
+        cw20_addr: Option<String>,
+        // Removes the whitelisted token, taking precedence over cw20_addr
+        remove_cw20: Option<bool>,
+        group_addr: Option<String>,
+        // Removes the group so anyone can vote again, taking precedence over group_addr
+        remove_group: Option<bool>,
+        members_only_create: Option<bool>,
+    },
+}
+
+// The VoteWeight of a new poll, with the contract addresses still to be validated
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteWeightMsg {
+    Equal,
+    Deposit { denom: String },
+    Cw20 { address: String },
+    Cw721 { address: String, per_token: bool },
+    Staked,
+}
+
+// The message embedded in a cw20 Send to this contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    Vote {
+        poll_id: String,
+        vote: Choice,
+        merkle_proof: Option<MerkleProof>,
+    },
+}
+
+// Proof that the voter is a leaf of the merkle tree of a poll
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MerkleProof {
+    // The weight in the voter's leaf, when it has one
+    pub weight: Option<Uint128>,
+    // Hex encoded sibling hashes, from the leaf up to the root
+    pub proof: Vec<String>,
+}
+
+// Either a single option, a list of options or, for Quadratic polls, pairs of option
+// and number of votes. Single choice votes can still be sent as a plain string.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum Choice {
+    Single(String),
+    Multiple(Vec<String>),
+    Allocation(Vec<(String, u64)>),
 }
 
 // 12 Query
@@ -75,9 +189,37 @@ pub enum ExecuteMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    AllPolls {},
-    Poll { poll_id: String },
-    Vote { poll_id: String, address: String },
+    Config {},
+    AllPolls {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    PollsByCreator {
+        creator: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Poll {
+        poll_id: String,
+    },
+    Vote {
+        poll_id: String,
+        address: String,
+    },
+    ListVotes {
+        poll_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    VotesByVoter {
+        address: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Only for RankedChoice polls, runs the instant runoff over the rankings of all ballots
+    RankedResult {
+        poll_id: String,
+    },
 }
 
 // 12 QueryMsg
@@ -92,9 +234,29 @@ pub enum QueryMsg {
 // + pub struct AllPollsResponse {
 // +     pub polls: Vec<Poll>,
 // + }
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ConfigResponse {
+    pub admin: Option<Addr>,
+    pub pending_admin: Option<Addr>,
+    pub max_options: u32,
+    pub max_question_len: u32,
+    pub max_option_len: u32,
+    pub max_poll_id_len: u32,
+    pub cw20_addr: Option<Addr>,
+    pub group_addr: Option<Addr>,
+    pub members_only_create: bool,
+}
+
+// Returned in the data of the CreatePoll response
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct CreatePollResponse {
+    pub poll_id: String,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct AllPollsResponse {
-    pub polls: Vec<Poll>,
+    // Pairs of poll id and poll, ordered by poll id
+    pub polls: Vec<(String, Poll)>,
 }
 
 // 13 Query
@@ -117,6 +279,33 @@ pub struct VoteResponse {
     pub vote: Option<Ballot>,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ListVotesResponse {
+    // Pairs of voter and ballot, page through them with the last voter as start_after
+    pub votes: Vec<(Addr, Ballot)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct VotesByVoterResponse {
+    // Pairs of poll id and ballot, ordered by poll id
+    pub votes: Vec<(String, Ballot)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RankedResultResponse {
+    pub rounds: Vec<RankedRound>,
+    // None when no ballots were cast or the last options are tied
+    pub winner: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RankedRound {
+    // Pairs of option and weight of the ballots ranking it highest among the remaining options
+    pub counts: Vec<(String, Uint128)>,
+    // The options with the fewest votes, which are dropped before the next round
+    pub eliminated: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub enum MigrateMsg {}
+pub struct MigrateMsg {}
@@ -1,11 +1,12 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Uint128};
 // 05 State
 // - use cw_storage_plus::Item;
 // + use cw_storage_plus::{Item, Map};
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cw_utils::Expiration;
 
 // 05 State
 // - #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -19,7 +20,20 @@ use cw_storage_plus::{Item, Map};
 // + }
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    pub admin: Addr,
+    // None once the admin has renounced their role
+    pub admin: Option<Addr>,
+    // Limits applied to new polls, lengths are in bytes
+    pub max_options: u32,
+    pub max_question_len: u32,
+    pub max_option_len: u32,
+    pub max_poll_id_len: u32,
+    // The only cw20 token new polls can be weighted by. Polls keep accepting votes
+    // with their token when it is changed.
+    pub cw20_addr: Option<Addr>,
+    // When set only members of this cw4 group can vote, with their member weight
+    pub group_addr: Option<Addr>,
+    // Whether only members of the group can create polls
+    pub members_only_create: bool,
 }
 
 // 05 State
@@ -33,7 +47,66 @@ pub struct Config {
 pub struct Poll {
     pub creator: Addr,
     pub question: String,
-    pub options: Vec<(String, u64)>,
+    // Pairs of option and the total weight of the ballots counted for it
+    pub options: Vec<(String, Uint128)>,
+    pub status: PollStatus,
+    // Votes are only accepted once start has been reached and before end is reached
+    pub start: Option<Expiration>,
+    pub end: Option<Expiration>,
+    pub voting_mode: VotingMode,
+    pub vote_weight: VoteWeight,
+    // The block height the poll was created at, group weights are taken at this height
+    // so they cannot be changed during the poll
+    pub snapshot_height: u64,
+    // Whether only the addresses in POLL_VOTERS can vote
+    pub allowlist: bool,
+    // Hex encoded root of a merkle tree of the addresses which can vote
+    pub merkle_root: Option<String>,
+}
+
+// A poll only accepts votes while it is Open.
+// Closed polls no longer accept votes. Once a poll is closed or has ended it can be
+// finalized, which marks its results as final.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PollStatus {
+    Open,
+    Closed,
+    Finalized,
+}
+
+// How many options a single ballot may select.
+// Under Approval voting every selected option is counted once.
+// Under RankedChoice voting the options are ranked by preference, only first preferences
+// are counted in the poll and the winner is found by instant runoff.
+// Under Quadratic voting ballots spend credits across options, n votes on an option cost n²
+// credits. The credits are the budget, or the deposit when no budget is set.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingMode {
+    SingleChoice,
+    Approval { max_choices: u32 },
+    RankedChoice,
+    Quadratic { budget: Option<Uint128> },
+}
+
+// How much a single ballot counts for.
+// Under Deposit and Cw20 weighting a ballot counts for the amount of tokens sent with it,
+// which are held by the contract until the poll stops accepting votes.
+// Cw20 votes are sent through the token contract, which has to be whitelisted in the Config
+// when the poll is created.
+// Under Cw721 weighting only holders of the collection can vote, with one vote per token
+// when per_token is set and one vote per holder otherwise. Either way the tokens a vote
+// is cast with are recorded, so they cannot vote again after a transfer.
+// Under Staked weighting a ballot counts for the total stake the voter has delegated.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteWeight {
+    Equal,
+    Deposit { denom: String },
+    Cw20 { address: Addr },
+    Cw721 { address: Addr, per_token: bool },
+    Staked,
 }
 
 // 05 State
@@ -43,7 +116,17 @@ pub struct Poll {
 // + }
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Ballot {
-    pub option: String,
+    pub poll_id: String,
+    // The selected options, a single one unless the poll uses Approval voting.
+    // Ordered from most to least preferred for RankedChoice voting.
+    pub options: Vec<String>,
+    // Pairs of option and the votes this ballot added to it, revoked again on a revote
+    pub allocation: Vec<(String, Uint128)>,
+    pub weight: Uint128,
+    // The part of the deposit which has not been withdrawn yet
+    pub deposit: Uint128,
+    // The NFTs the ballot was cast with, released again when a revote no longer uses them
+    pub token_ids: Vec<String>,
 }
 
 // 05 State
@@ -51,17 +134,100 @@ pub struct Ballot {
 // + pub const CONFIG: Item<Config> = Item::new("config");
 pub const CONFIG: Item<Config> = Item::new("config");
 
+// The proposed admin, who only becomes the admin after accepting
+pub const PENDING_ADMIN: Item<Addr> = Item::new("pending_admin");
+
 // 05 State
 // + pub const POLLS: Map<String, Poll> = Map::new("polls");
 // + pub const BALLOTS: Map<(Addr, String), Ballot> = Map::new("ballots");
 // 15 Spring Cleaning
 // - pub const POLLS: Map<String, Poll> = Map::new("polls");
 // + pub const POLLS: Map<&str, Poll> = Map::new("polls");
-// A map with a String key and Poll value.
-// The key will be a UUID generated clientside
-pub const POLLS: Map<&str, Poll> = Map::new("polls");
+// A map with a String key and Poll value, indexed by the creator of the poll.
+// The key is either chosen by the client or the next number of POLL_COUNT assigned
+// by the contract
+pub struct PollIndexes<'a> {
+    pub creator: MultiIndex<'a, Addr, Poll, String>,
+}
+
+impl<'a> IndexList<Poll> for PollIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Poll>> + '_> {
+        let v: Vec<&dyn Index<Poll>> = vec![&self.creator];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn polls<'a>() -> IndexedMap<'a, &'a str, Poll, PollIndexes<'a>> {
+    let indexes = PollIndexes {
+        creator: MultiIndex::new(
+            |poll: &Poll| poll.creator.clone(),
+            "polls",
+            "polls__creator",
+        ),
+    };
+    IndexedMap::new("polls", indexes)
+}
+
+// A map with a (poll id, token id) key and the voter who used the NFT as value,
+// so a transferred NFT cannot be used to vote again
+pub const NFT_VOTES: Map<(&str, &str), Addr> = Map::new("nft_votes");
+
+// A map with a (poll id, voter) key for the addresses allowed to vote on a poll with an allowlist
+pub const POLL_VOTERS: Map<(&str, &Addr), ()> = Map::new("poll_voters");
+
+// A map with a (poll id, ranking) key and the total weight of the ballots with that ranking,
+// for RankedChoice polls. A ranking is the positions of its options in the poll, e.g. "2,0,1".
+pub const RANKED_VOTES: Map<(&str, &str), Uint128> = Map::new("ranked_votes");
+
+// The number of polls which have been assigned an id by the contract
+pub const POLL_COUNT: Item<u64> = Item::new("poll_count");
 
 // 15 Spring Cleaning
 // - pub const BALLOTS: Map<(Addr, String), Ballot> = Map::new("ballots");
 // + pub const BALLOTS: Map<(Addr, &str), Ballot> = Map::new("ballots");
-pub const BALLOTS: Map<(Addr, &str), Ballot> = Map::new("ballots");
+// A map with a (voter, poll id) key and Ballot value, indexed by the poll id
+// so all ballots of a single poll can be listed.
+pub struct BallotIndexes<'a> {
+    pub poll: MultiIndex<'a, String, Ballot, (Addr, String)>,
+}
+
+impl<'a> IndexList<Ballot> for BallotIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Ballot>> + '_> {
+        let v: Vec<&dyn Index<Ballot>> = vec![&self.poll];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn ballots<'a>() -> IndexedMap<'a, (Addr, &'a str), Ballot, BallotIndexes<'a>> {
+    let indexes = BallotIndexes {
+        poll: MultiIndex::new(
+            |ballot: &Ballot| ballot.poll_id.clone(),
+            "ballots",
+            "ballots__poll",
+        ),
+    };
+    IndexedMap::new("ballots", indexes)
+}
+
+// State layout of 0.1.x, the config had no limits, polls had no status or schedule
+// and ballots did not store their poll id. Only read when migrating.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyConfig {
+    pub admin: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyPoll {
+    pub creator: Addr,
+    pub question: String,
+    pub options: Vec<(String, u64)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyBallot {
+    pub option: String,
+}
+
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+pub const LEGACY_POLLS: Map<&str, LegacyPoll> = Map::new("polls");
+pub const LEGACY_BALLOTS: Map<(Addr, &str), LegacyBallot> = Map::new("ballots");